@@ -0,0 +1,445 @@
+// Arbitrary precision iteration: every sample is iterated in fixed point numbers with a
+// configurable number of fractional bits. Far slower than Real, but exact up to the last bit
+// at any zoom, which makes it the reference the other deep zoom paths can be checked against
+use std::cmp::Ordering;
+use std::sync::atomic::AtomicUsize;
+use rand::Rng;
+use super::{Color, ColoringMode, Complex, FractalKind, Orbit, Palette, Prepass, Real, RenderConfig, ThreadDescryptor, Work, shade_rows};
+
+// 32 bit limbs in front of the binary point, enough for z^d of any orbit that hasn't escaped yet
+const INTEGER_LIMBS: usize = 2;
+// Smallest and largest --precision accepted
+const MIN_PRECISION: u32 = 32;
+const MAX_PRECISION: u32 = 4096;
+// Largest decimal exponent of a parsed number, past it every digit is lost at MAX_PRECISION anyway
+const MAX_DECIMAL_EXPONENT: i32 = 1500;
+
+// Sign and magnitude fixed point number, limbs are little endian and the lowest
+// limbs.len() - INTEGER_LIMBS of them are the fraction
+#[derive(Clone, Debug, PartialEq)]
+struct BigFixed {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigFixed {
+    fn zero(fraction_limbs: usize) -> BigFixed {
+        BigFixed {negative: false, limbs: vec![0; fraction_limbs + INTEGER_LIMBS]}
+    }
+
+    // Zero is always positive, so a result that cancels or truncates to zero
+    // still compares equal to every other zero
+    fn signed(negative: bool, limbs: Vec<u32>) -> BigFixed {
+        let negative = negative && limbs.iter().any(|&limb| limb != 0);
+        BigFixed {negative, limbs}
+    }
+
+    // Exact conversion, bits below the last fraction limb are dropped
+    fn from_f64(value: f64, fraction_limbs: usize) -> BigFixed {
+        let mut result = BigFixed::zero(fraction_limbs);
+        let mut rest = value.abs();
+        for index in (0..result.limbs.len()).rev() {
+            if rest == 0.0 {
+                break;
+            }
+            let weight = 2f64.powi(32 * (index as i32 - fraction_limbs as i32));
+            let limb = (rest / weight).floor();
+            result.limbs[index] = limb as u32;
+            rest -= limb * weight;
+        }
+        BigFixed::signed(value < 0.0, result.limbs)
+    }
+
+    // Parses a decimal like -0.75, +.5 or 1.25e-3 without rounding it to a float first,
+    // digits past the last fraction limb are truncated
+    fn parse(text: &str, fraction_limbs: usize) -> Result<BigFixed, String> {
+        let invalid = || format!("{} is not a decimal number", text);
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(index) => (&unsigned[..index], unsigned[index + 1..].parse::<i32>().map_err(|_| invalid())?),
+            None => (unsigned, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if (integer.is_empty() && fraction.is_empty()) || !integer.bytes().chain(fraction.bytes()).all(|digit| digit.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        if exponent.abs() > MAX_DECIMAL_EXPONENT {
+            return Err(format!("the exponent of {} is out of range", text));
+        }
+
+        // Moves the decimal point by the exponent
+        let mut digits = format!("{}{}", integer, fraction);
+        let mut point = integer.len() as i64 + exponent as i64;
+        if point < 0 {
+            digits = "0".repeat(-point as usize) + &digits;
+            point = 0;
+        }
+        if point as usize > digits.len() {
+            digits += &"0".repeat(point as usize - digits.len());
+        }
+        let (integer, fraction) = digits.split_at(point as usize);
+
+        // The fraction from its last digit on, every step adds the digit in front of the
+        // binary point and divides by 10
+        let mut result = BigFixed::zero(fraction_limbs);
+        for digit in fraction.bytes().rev() {
+            result.limbs[fraction_limbs] = (digit - b'0') as u32;
+            let mut remainder = 0u64;
+            for limb in result.limbs.iter_mut().rev() {
+                let value = (remainder << 32) | *limb as u64;
+                *limb = (value / 10) as u32;
+                remainder = value % 10;
+            }
+        }
+        let whole = integer.bytes().try_fold(0u64, |whole, digit| whole.checked_mul(10)?.checked_add((digit - b'0') as u64))
+            .filter(|&whole| whole < 1 << (32 * INTEGER_LIMBS - 1))
+            .ok_or_else(|| format!("{} is out of range", text))?;
+        for index in 0..INTEGER_LIMBS {
+            result.limbs[fraction_limbs + index] = whole.checked_shr(32 * index as u32).unwrap_or(0) as u32;
+        }
+        Ok(BigFixed::signed(negative, result.limbs))
+    }
+
+    fn to_f64(&self) -> f64 {
+        let mut weight = 2f64.powi(32 * (INTEGER_LIMBS as i32 - 1));
+        let mut magnitude = 0.0;
+        for &limb in self.limbs.iter().rev() {
+            magnitude += limb as f64 * weight;
+            weight /= 4294967296.0;
+        }
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    fn compare_magnitude(&self, other: &BigFixed) -> Ordering {
+        self.limbs.iter().rev().cmp(other.limbs.iter().rev())
+    }
+
+    fn add_magnitude(&self, other: &BigFixed) -> Vec<u32> {
+        let mut carry = 0u64;
+        self.limbs.iter().zip(&other.limbs).map(|(&a, &b)| {
+            let sum = a as u64 + b as u64 + carry;
+            carry = sum >> 32;
+            sum as u32
+        }).collect()
+    }
+
+    // |self| - |other|, |self| has to be the larger one
+    fn sub_magnitude(&self, other: &BigFixed) -> Vec<u32> {
+        let mut borrow = 0i64;
+        self.limbs.iter().zip(&other.limbs).map(|(&a, &b)| {
+            let difference = a as i64 - b as i64 - borrow;
+            borrow = if difference < 0 { 1 } else { 0 };
+            difference.rem_euclid(1 << 32) as u32
+        }).collect()
+    }
+
+    fn add(&self, other: &BigFixed) -> BigFixed {
+        if self.negative == other.negative {
+            return BigFixed::signed(self.negative, self.add_magnitude(other));
+        }
+        match self.compare_magnitude(other) {
+            Ordering::Less => BigFixed::signed(other.negative, other.sub_magnitude(self)),
+            _ => BigFixed::signed(self.negative, self.sub_magnitude(other)),
+        }
+    }
+
+    fn neg(&self) -> BigFixed {
+        BigFixed::signed(!self.negative, self.limbs.clone())
+    }
+
+    fn sub(&self, other: &BigFixed) -> BigFixed {
+        self.add(&other.neg())
+    }
+
+    fn abs(&self) -> BigFixed {
+        BigFixed {negative: false, limbs: self.limbs.clone()}
+    }
+
+    // Schoolbook product truncated back to the fraction limbs
+    fn mul(&self, other: &BigFixed) -> BigFixed {
+        let length = self.limbs.len();
+        let fraction_limbs = length - INTEGER_LIMBS;
+        let mut product = vec![0u64; 2 * length];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let sum = product[i + j] + a as u64 * b as u64 + carry;
+                product[i + j] = sum & 0xffff_ffff;
+                carry = sum >> 32;
+            }
+            product[i + length] += carry;
+        }
+        let limbs = product[fraction_limbs..fraction_limbs + length].iter().map(|&limb| limb as u32).collect();
+        BigFixed::signed(self.negative != other.negative, limbs)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BigComplex {
+    r: BigFixed,
+    i: BigFixed,
+}
+
+impl BigComplex {
+    fn add(&self, other: &BigComplex) -> BigComplex {
+        BigComplex {r: self.r.add(&other.r), i: self.i.add(&other.i)}
+    }
+
+    fn mul(&self, other: &BigComplex) -> BigComplex {
+        BigComplex {
+            r: self.r.mul(&other.r).sub(&self.i.mul(&other.i)),
+            i: self.r.mul(&other.i).add(&self.i.mul(&other.r)),
+        }
+    }
+
+    // Same multiplication order as Complex::powi, squares take three products instead of four
+    fn powi(&self, n: u32) -> BigComplex {
+        if n == 2 {
+            let ri = self.r.mul(&self.i);
+            return BigComplex {r: self.r.mul(&self.r).sub(&self.i.mul(&self.i)), i: ri.add(&ri)};
+        }
+        let mut result = self.clone();
+        for _ in 1..n {
+            result = result.mul(self);
+        }
+        result
+    }
+
+    fn to_complex(&self) -> Complex {
+        Complex {r: self.r.to_f64() as Real, i: self.i.to_f64() as Real}
+    }
+}
+
+// Fraction limbs holding at least bits bits
+fn fraction_limbs(bits: u32) -> usize {
+    bits.div_ceil(32) as usize
+}
+
+// Checks that text is a decimal number the fixed point numbers can hold
+pub(crate) fn check_decimal(text: &str) -> Result<(), String> {
+    BigFixed::parse(text, 1).map(|_| ())
+}
+
+// Center of the view of config in bits of precision, from its decimal digits as long as they
+// still round to config.center, the view may have been moved since they were set
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn view_center(config: &RenderConfig, bits: u32) -> BigComplex {
+    let fraction_limbs = fraction_limbs(bits);
+    let exact = |digits: &str, rounded: Real| match digits.parse::<Real>() {
+        Ok(value) if value == rounded => BigFixed::parse(digits, fraction_limbs).ok(),
+        _ => None,
+    };
+    let from_digits = config.center_digits.as_ref().and_then(|(x, y)| Some(BigComplex {r: exact(x, config.center.x)?, i: exact(y, config.center.y)?}));
+    from_digits.unwrap_or_else(|| BigComplex {
+        r: BigFixed::from_f64(config.center.x as f64, fraction_limbs),
+        i: BigFixed::from_f64(config.center.y as f64, fraction_limbs),
+    })
+}
+
+// Checks that the fixed point numbers can hold every orbit config can produce
+pub(crate) fn validate(bits: u32, fractal: &FractalKind, exponent: u32, bailout: Real) -> Result<(), String> {
+    if !(MIN_PRECISION..=MAX_PRECISION).contains(&bits) {
+        return Err(format!("precision must be between {} and {} bits, got {}", MIN_PRECISION, MAX_PRECISION, bits));
+    }
+    if matches!(fractal, FractalKind::Buddhabrot | FractalKind::Newton | FractalKind::Nova {..}) {
+        return Err(String::from("arbitrary precision only supports the escape time fractals"));
+    }
+    // z^d of the last orbit point inside the bailout has to fit the integer limbs
+    if exponent == 0 || bailout.max(1.0).powi(exponent as i32) >= (2.0 as Real).powi(32 * INTEGER_LIMBS as i32 - 1) {
+        return Err(format!("arbitrary precision can't hold orbits of bailout {} and power {}", bailout, exponent));
+    }
+    Ok(())
+}
+
+// Iterates the sample at pixel coordinates x, y around center, see view_center, like iterate
+// without the cardioid shortcut whose test would round the position to Real
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn iterate(desc: &ThreadDescryptor, center: &BigComplex, x: f64, y: f64) -> Orbit {
+    let fraction_limbs = center.r.limbs.len() - INTEGER_LIMBS;
+    let size = &desc.color_buffer_size;
+    let pos = BigComplex {
+        r: center.r.add(&BigFixed::from_f64((x / size.x as f64 - 0.5) * desc.view_size.x as f64, fraction_limbs)),
+        i: center.i.add(&BigFixed::from_f64(-(y / size.y as f64 - 0.5) * desc.view_size.y as f64, fraction_limbs)),
+    };
+    let zero = BigComplex {r: BigFixed::zero(fraction_limbs), i: BigFixed::zero(fraction_limbs)};
+    let (mut z, c) = match desc.fractal {
+        FractalKind::Julia {c} => (pos, BigComplex {r: BigFixed::from_f64(c.r as f64, fraction_limbs), i: BigFixed::from_f64(c.i as f64, fraction_limbs)}),
+        _ => (zero, pos),
+    };
+    let (mut dz, dc) = match desc.fractal {
+        FractalKind::Julia {..} => (Complex {r: 1.0, i: 0.0}, Complex {r: 0.0, i: 0.0}),
+        _ => (Complex {r: 0.0, i: 0.0}, Complex {r: 1.0, i: 0.0}),
+    };
+    let track_derivative = matches!(desc.coloring, ColoringMode::Distance | ColoringMode::Slope);
+    let trap = match desc.coloring {
+        ColoringMode::OrbitTrap {trap} => Some(trap),
+        _ => None,
+    };
+    let mut trap_distance = Real::INFINITY;
+
+    // The escape test only needs the magnitude, which f64 holds for any orbit inside the bailout
+    let bailout_squared = (desc.bailout as f64) * (desc.bailout as f64);
+    let length_squared = |z: &BigComplex| {
+        let (r, i) = (z.r.to_f64(), z.i.to_f64());
+        r*r + i*i
+    };
+    let mut reference = z.clone();
+    let mut check_interval = super::PERIODICITY_CHECK_INTERVAL;
+    let mut steps_since_reference = 0;
+    let mut iterations = 0;
+    let mut computed = 0;
+    while length_squared(&z) <= bailout_squared && iterations < desc.max_iterations {
+        if track_derivative {
+            let d = Complex {r: desc.exponent as Real, i: 0.0};
+            dz = d * z.to_complex().powi(desc.exponent.saturating_sub(1)) * dz + dc;
+        }
+        let base = match desc.fractal {
+            FractalKind::BurningShip => BigComplex {r: z.r.abs(), i: z.i.abs()},
+            FractalKind::Tricorn => BigComplex {r: z.r.clone(), i: z.i.neg()},
+            _ => z,
+        };
+        z = base.powi(desc.exponent).add(&c);
+        iterations += 1;
+        computed += 1;
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance(&z.to_complex()));
+        }
+
+        if z == reference {
+            iterations = desc.max_iterations;
+            break;
+        }
+        steps_since_reference += 1;
+        if steps_since_reference == check_interval {
+            reference = z.clone();
+            steps_since_reference = 0;
+            check_interval *= 2;
+        }
+    }
+    Orbit {iterations, computed, escaped: length_squared(&z) > bailout_squared, z: z.to_complex(), dz, trap_distance, interior_distance: None}
+}
+
+// Same as thread_worker with every sample iterated in the precision of center
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn thread_worker<R: Rng>(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, center: &BigComplex, palette: &Palette, prepass: &Prepass, pixels_done: &AtomicUsize, rng: &mut R) -> Work {
+    shade_rows(rows, desc, palette, prepass, pixels_done, rng, |x, y, jitter| iterate(desc, center, x as f64 + jitter.x as f64, y as f64 + jitter.y as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{RenderConfig, Vec2, pixel_position};
+    use super::super::perturbation::ReferenceOrbit;
+
+    #[test]
+    fn fixed_point_arithmetic_is_exact() {
+        let a = BigFixed::from_f64(-1.5, 3);
+        let b = BigFixed::from_f64(0.25 + 2f64.powi(-90), 3);
+        assert_eq!(a.to_f64(), -1.5);
+        assert_eq!(a.add(&b).to_f64(), -1.25 + 2f64.powi(-90));
+        assert_eq!(b.sub(&a).to_f64(), 1.75 + 2f64.powi(-90));
+        assert_eq!(a.mul(&b).to_f64(), -0.375 - 1.5 * 2f64.powi(-90));
+        assert_eq!(a.mul(&a).to_f64(), 2.25);
+        // The smallest fraction bit survives where f64 would round it away
+        assert_eq!(BigFixed::from_f64(1.0, 3).add(&BigFixed::from_f64(2f64.powi(-96), 3)).limbs[0], 1);
+    }
+
+    #[test]
+    fn zero_has_a_single_sign() {
+        let zero = BigFixed::zero(3);
+        let a = BigFixed::from_f64(-1.5, 3);
+        assert_eq!(a.mul(&zero), zero);
+        assert_eq!(zero.mul(&a), zero);
+        assert_eq!(a.sub(&a), zero);
+        assert_eq!(zero.neg(), zero);
+        // Products below the last fraction bit truncate to zero too
+        let tiny = BigFixed::from_f64(-2f64.powi(-60), 3);
+        assert_eq!(tiny.mul(&tiny.neg()), zero);
+        assert_eq!(BigFixed::from_f64(-2f64.powi(-200), 3), zero);
+    }
+
+    #[test]
+    fn periodicity_check_stops_attracting_cycles_early() {
+        let mut config = RenderConfig::new();
+        config.width = 1;
+        config.height = 1;
+        config.max_iterations = 100_000;
+        config.set_zoom(1e-20);
+        // The period-2 cycle on the real axis keeps its imaginary part at zero, the rabbit has period 3
+        for c in [Vec2::<Real>{x: -1.0, y: 0.0}, Vec2::<Real>{x: -1.1, y: 0.0}, Vec2::<Real>{x: -0.1226, y: 0.7449}] {
+            config.center = c;
+            let desc = ThreadDescryptor::from_config(&config);
+            let orbit = iterate(&desc, &view_center(&config, 64), 0.5, 0.5);
+            assert!(!orbit.escaped && orbit.iterations == config.max_iterations);
+            assert!(orbit.computed < 1000, "{} iterations computed for {:?}", orbit.computed, c);
+        }
+    }
+
+    #[test]
+    fn decimals_parse_without_rounding() {
+        let parse = |text: &str| BigFixed::parse(text, 3).unwrap();
+        assert_eq!(parse("-1.5"), BigFixed::from_f64(-1.5, 3));
+        assert_eq!(parse("+.25"), BigFixed::from_f64(0.25, 3));
+        assert_eq!(parse("25e-2"), parse("0.0025E2"));
+        assert_eq!(parse("-0"), BigFixed::zero(3));
+        assert_eq!(parse("8.07793566946316088741610050849573099185363389551639556884765625e-28"), BigFixed::from_f64(2f64.powi(-90), 3));
+        // 0.1 + 1e-27 rounds to 0.1 in f64
+        assert_ne!(parse("0.100000000000000000000000001"), BigFixed::from_f64(0.1, 3));
+        for text in ["", "-", ".", "1.2.3", "1e", "e5", "0x10", "inf", "1,5", "1e9999", "1e30"] {
+            assert!(BigFixed::parse(text, 3).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn view_center_keeps_the_digits_of_the_center() {
+        let mut config = RenderConfig::new();
+        config.set_center_digits("-1.76938317919551501821384728608", "0.00423684791873677221").unwrap();
+        let digits = BigFixed::parse("-1.76938317919551501821384728608", fraction_limbs(128)).unwrap();
+        assert_eq!(view_center(&config, 128).r, digits);
+        // Moving the view drops them
+        config.center.x = 0.5;
+        assert_eq!(view_center(&config, 128).r, BigFixed::from_f64(0.5, fraction_limbs(128)));
+        assert!(config.set_center_digits("1.0", "nan").is_err());
+    }
+
+    // Share of the pixels of config where both iterations agree on the escape time
+    fn matching_share(config: &RenderConfig, orbit: impl Fn(&ThreadDescryptor, usize, usize) -> Orbit) -> f64 {
+        let desc = ThreadDescryptor::from_config(config);
+        let center = view_center(config, 64);
+        let mut matching = 0;
+        for y in 0..config.height {
+            for x in 0..config.width {
+                let (expected, orbit) = (iterate(&desc, &center, x as f64, y as f64), orbit(&desc, x, y));
+                if orbit.escaped == expected.escaped && orbit.iterations == expected.iterations {
+                    matching += 1;
+                }
+            }
+        }
+        matching as f64 / (config.width * config.height) as f64
+    }
+
+    #[test]
+    fn validates_the_direct_and_perturbation_iterations() {
+        let mut config = RenderConfig::new();
+        config.width = 16;
+        config.height = 12;
+        config.center = Vec2::<Real>{x: -0.75, y: 0.1};
+        config.set_zoom(0.05);
+        config.max_iterations = 300;
+        let share = matching_share(&config, |desc, x, y| super::super::iterate(desc, &pixel_position(desc, x, y, Vec2::<Real>::new())));
+        assert!(share >= 0.95, "{} of the direct iterations match", share);
+
+        // Deep zooms are checked against the perturbation, the boundary passes through i at every zoom
+        config.center = Vec2::<Real>{x: 0.0, y: 1.0};
+        config.set_zoom(1e-12);
+        config.max_iterations = 1000;
+        let mut whole_image = ThreadDescryptor::from_config(&config);
+        whole_image.thread_size = whole_image.color_buffer_size;
+        let reference = ReferenceOrbit::longest(&whole_image);
+        let share = matching_share(&config, |desc, x, y| reference.iterate(desc, x as f64, y as f64));
+        assert!(share >= 0.95, "{} of the perturbation iterations match", share);
+    }
+}
@@ -0,0 +1,223 @@
+// Buddhabrot rendering: instead of coloring a pixel by its own escape time,
+// every escaping orbit leaves a hit on each pixel it passes through
+use std::sync::{Mutex, atomic::{AtomicBool, Ordering}, mpsc::channel};
+use std::thread;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use super::{Color, Complex, RenderConfig, Real, MAX_LENGTH, in_main_cardioid_or_bulb, min, ProgressReport, SPAWN_WORKERS, worker_count};
+
+// Number of orbit batches the samples are split into, workers pull batches like tiles
+const BATCH_COUNT: usize = 256;
+
+// Escape time thresholds of the red, green and blue exposures, an orbit is
+// recorded in a channel if it escapes after min_iterations and within that
+// channel's max_iterations
+#[derive(Copy, Clone, Debug)]
+pub struct BuddhabrotConfig {
+    pub min_iterations: u32,
+    pub max_iterations: [u32; 3],
+}
+
+impl BuddhabrotConfig {
+    pub fn new() -> BuddhabrotConfig {
+        BuddhabrotConfig {
+            min_iterations: 0,
+            max_iterations: [5000, 500, 50],
+        }
+    }
+}
+
+impl Default for BuddhabrotConfig {
+    fn default() -> BuddhabrotConfig {
+        BuddhabrotConfig::new()
+    }
+}
+
+// Hits of every pixel in the red, green and blue exposures
+type Exposures = Vec<[u32; 3]>;
+
+// Index of the pixel containing z, None if z is outside the view
+fn pixel_index(config: &RenderConfig, z: &Complex) -> Option<usize> {
+    let x = ((z.r - config.center.x) / config.view_size.x + 0.5) * (config.width as Real);
+    let view_height = if config.flip_y { -config.view_size.y } else { config.view_size.y };
+    let y = (0.5 - (z.i - config.center.y) / view_height) * (config.height as Real);
+    if x < 0.0 || y < 0.0 || x >= config.width as Real || y >= config.height as Real {
+        return None;
+    }
+    Some((y as usize) * config.width + (x as usize))
+}
+
+// Traces orbit_count random orbits into hits, returns the iterations of both passes
+fn trace_orbits<R: Rng>(config: &RenderConfig, hits: &mut Exposures, orbit_count: usize, rng: &mut R) -> u64 {
+    let thresholds = config.buddhabrot;
+    let max_iterations = thresholds.max_iterations.iter().copied().max().unwrap_or(0);
+    let bailout_squared = config.bailout*config.bailout;
+
+    let mut total_iterations = 0;
+    for _ in 0..orbit_count {
+        // Every escaping orbit starts inside the radius 2 disk
+        let c = Complex {r: rng.gen_range(-MAX_LENGTH, MAX_LENGTH), i: rng.gen_range(-MAX_LENGTH, MAX_LENGTH)};
+        if config.exponent == 2 && in_main_cardioid_or_bulb(&c) {
+            continue;
+        }
+
+        // First pass finds whether and when the orbit escapes
+        let mut z = Complex {r: 0.0, i: 0.0};
+        let mut iterations: u32 = 0;
+        while z.length_squared() <= bailout_squared && iterations < max_iterations {
+            z = z.powi(config.exponent) + c;
+            iterations += 1;
+        }
+        total_iterations += iterations as u64;
+        if z.length_squared() <= bailout_squared || iterations < thresholds.min_iterations {
+            continue;
+        }
+        let channels = thresholds.max_iterations.map(|max| iterations <= max);
+        total_iterations += iterations as u64;
+
+        // Second pass replays the orbit and records it in the matching exposures
+        let mut z = Complex {r: 0.0, i: 0.0};
+        for _ in 0..iterations {
+            z = z.powi(config.exponent) + c;
+            if let Some(index) = pixel_index(config, &z) {
+                for channel in 0..3 {
+                    if channels[channel] {
+                        hits[index][channel] += 1;
+                    }
+                }
+            }
+        }
+    }
+    total_iterations
+}
+
+// Traces sample_count orbits per pixel and normalizes each exposure by its brightest pixel,
+// returns the image and the iterations performed
+pub(crate) fn render_buddhabrot(config: &RenderConfig, interrupted: &AtomicBool, progress: &mut dyn FnMut(f32)) -> (Vec<Color>, u64) {
+    let pixel_count = config.width * config.height;
+    let orbit_count = pixel_count * config.sample_count;
+
+    let mut report = match config.progress {
+        true => Some(ProgressReport::start()),
+        false => None,
+    };
+
+    // Every worker accumulates into its own exposures, merged once all batches are done
+    let queue = Mutex::new(0..BATCH_COUNT);
+    let (sender, receiver) = channel::<u64>();
+    let mut iterations = 0;
+    let mut hits: Exposures = vec![[0; 3]; pixel_count];
+    thread::scope(|scope| {
+        let mut workers = Vec::new();
+        let mut finished_workers = Vec::new();
+        for _ in 0..min(BATCH_COUNT, worker_count(config)) {
+            let queue = &queue;
+            let sender = sender.clone();
+            let worker = move || {
+                let mut worker_hits: Exposures = vec![[0; 3]; pixel_count];
+                loop {
+                    if interrupted.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let next_batch = queue.lock().unwrap().next();
+                    match next_batch {
+                        Some(batch) => {
+                            let batch_orbits = orbit_count * (batch + 1) / BATCH_COUNT - orbit_count * batch / BATCH_COUNT;
+                            let batch_iterations = match config.seed {
+                                Some(seed) => trace_orbits(config, &mut worker_hits, batch_orbits, &mut StdRng::seed_from_u64(seed ^ batch as u64)),
+                                None => trace_orbits(config, &mut worker_hits, batch_orbits, &mut rand::thread_rng()),
+                            };
+                            sender.send(batch_iterations).unwrap();
+                        }
+                        None => break,
+                    }
+                }
+                worker_hits
+            };
+            // Threads can't be spawned on wasm32, its single worker traces every batch
+            // on the calling thread before they are collected
+            if SPAWN_WORKERS {
+                workers.push(scope.spawn(worker));
+            }
+            else {
+                finished_workers.push(worker());
+            }
+        }
+
+        // The channel closes once every worker has stopped
+        drop(sender);
+        for (finished_batches, batch_iterations) in receiver.iter().enumerate() {
+            let finished_batches = finished_batches + 1;
+            iterations += batch_iterations;
+            progress(finished_batches as f32 / BATCH_COUNT as f32);
+            if let Some(report) = &mut report {
+                let percent = finished_batches*100/BATCH_COUNT;
+                report.print(percent as u32, finished_batches);
+            }
+        }
+
+        finished_workers.extend(workers.into_iter().map(|worker| worker.join().unwrap()));
+        for worker_hits in finished_workers {
+            for (total, worker_total) in hits.iter_mut().zip(worker_hits) {
+                for channel in 0..3 {
+                    total[channel] += worker_total[channel];
+                }
+            }
+        }
+    });
+
+    let mut brightest = [0u32; 3];
+    for pixel in &hits {
+        for channel in 0..3 {
+            brightest[channel] = brightest[channel].max(pixel[channel]);
+        }
+    }
+    let normalize = |value: u32, channel: usize| -> Real {
+        if brightest[channel] == 0 {
+            return 0.0;
+        }
+        (value as Real) / (brightest[channel] as Real)
+    };
+    let color_buffer = hits.iter().map(|pixel| Color::rgb(normalize(pixel[0], 0), normalize(pixel[1], 1), normalize(pixel[2], 2))).collect();
+    (color_buffer, iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{FractalKind, Vec2};
+
+    fn seeded_buddhabrot(thread_count: usize) -> Vec<Color> {
+        let mut config = RenderConfig::new();
+        config.fractal = FractalKind::Buddhabrot;
+        config.width = 32;
+        config.height = 32;
+        config.center = Vec2::<Real>{x: -0.5, y: 0.0};
+        config.set_zoom(3.0);
+        config.sample_count = 64;
+        config.buddhabrot.max_iterations = [500, 100, 20];
+        config.seed = Some(19);
+        config.thread_count = thread_count;
+        config.progress = false;
+        render_buddhabrot(&config, &AtomicBool::new(false), &mut |_| {}).0
+    }
+
+    #[test]
+    fn seeded_renders_match_across_thread_counts() {
+        let single = seeded_buddhabrot(1);
+        assert_eq!(single, seeded_buddhabrot(1));
+        assert_eq!(single, seeded_buddhabrot(3));
+        assert!(single.iter().any(|color| color.r > 0.0 && color.b > 0.0));
+
+        // The orbit of the conjugate of c is the conjugate of the orbit of c, so with enough random
+        // orbits the rows above the real axis add up to about the same exposure as the rows below
+        let half = single.len() / 2;
+        let exposure = |pixels: &[Color]| pixels.iter().map(|color| color.r + color.g + color.b).sum::<Real>();
+        let (above, below) = (exposure(&single[..half]), exposure(&single[half..]));
+        assert!((above - below).abs() < 0.01 * (above + below), "{} above and {} below the real axis", above, below);
+        for y in 0..16 {
+            let row = exposure(&single[y*32..(y + 1)*32]);
+            let mirrored = exposure(&single[(31 - y)*32..(32 - y)*32]);
+            assert!((row - mirrored).abs() <= 0.05 * (row + mirrored), "row {}: {} and {}", y, row, mirrored);
+        }
+    }
+}
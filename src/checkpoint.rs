@@ -0,0 +1,231 @@
+// Progress of a tiled render, saved to disk so an interrupted render can be resumed
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use super::{Color, Region, RenderConfig, Real, ThreadDescryptor, TileOrder, tile_count, with_path};
+
+// Version 2 stores the channels as f64, version 1 rounded them to f32
+const MAGIC: &str = "mandelbrot_set checkpoint 2";
+// Bytes of an RGBA pixel of the payload
+const PIXEL_BYTES: usize = 4 * 8;
+
+pub struct Checkpoint {
+    // Identifies the config the tiles were rendered with
+    fingerprint: String,
+    pub finished_tiles: Vec<bool>,
+    pub color_buffer: Vec<Color>,
+}
+
+// Every config field that changes the rendered pixels, the thread count, tile order and the progress and tile output don't
+pub(crate) fn fingerprint(config: &RenderConfig) -> String {
+    let mut config = config.clone();
+    config.thread_count = 0;
+    config.tile_order = TileOrder::Row;
+    config.progress = true;
+    config.verbose = false;
+    format!("{:?}", config)
+}
+
+impl Checkpoint {
+    // Checkpoint of a render of config that hasn't started yet
+    pub fn new(config: &RenderConfig) -> Checkpoint {
+        let region = config.output_region();
+        Checkpoint {
+            fingerprint: fingerprint(config),
+            finished_tiles: vec![false; tile_count(config)],
+            color_buffer: vec![Color::new(); region.width * region.height],
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.finished_tiles.iter().all(|&finished| finished)
+    }
+
+    // Copies the row major pixels of a finished tile into the buffer of the rendered region
+    pub(crate) fn store_tile(&mut self, id: usize, desc: &ThreadDescryptor, pixels: &[Color], region: &Region) {
+        for (y, row) in pixels.chunks(desc.thread_size.x).enumerate() {
+            let start = (desc.offset.y - region.y + y) * region.width + desc.offset.x - region.x;
+            self.color_buffer[start..start + row.len()].copy_from_slice(row);
+        }
+        self.finished_tiles[id] = true;
+    }
+
+    // Writes the checkpoint to a temporary file first, so a crash while saving keeps the previous one.
+    // Channels are stored as f64, which holds every Real exactly so resumed tiles match a straight render
+    #[allow(clippy::unnecessary_cast)]
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let temp_path = format!("{}.tmp", path);
+        let file = fs::File::create(&temp_path).map_err(|e| with_path(&temp_path, e))?;
+        let mut w = BufWriter::new(file);
+
+        let finished: String = self.finished_tiles.iter().map(|&finished| if finished { '1' } else { '0' }).collect();
+        write!(w, "{}\n{}\n{}\n", MAGIC, self.fingerprint, finished).map_err(|e| with_path(&temp_path, e))?;
+        for color in &self.color_buffer {
+            for channel in &[color.r, color.g, color.b, color.a] {
+                w.write_all(&(*channel as f64).to_le_bytes()).map_err(|e| with_path(&temp_path, e))?;
+            }
+        }
+        w.flush().map_err(|e| with_path(&temp_path, e))?;
+        drop(w);
+        fs::rename(&temp_path, path).map_err(|e| with_path(path, e))
+    }
+
+    // Loads a checkpoint saved by a render of config, fails if it was made with any other config
+    #[allow(clippy::unnecessary_cast)]
+    pub fn load(path: &str, config: &RenderConfig) -> io::Result<Checkpoint> {
+        let invalid = |message: &str| with_path(path, io::Error::new(io::ErrorKind::InvalidData, message.to_string()));
+        let data = fs::read(path).map_err(|e| with_path(path, e))?;
+
+        // Three header lines followed by the RGBA f64 pixels
+        let mut lines = data.splitn(4, |&byte| byte == b'\n');
+        let magic = lines.next().unwrap_or_default();
+        let saved_fingerprint = lines.next().ok_or_else(|| invalid("truncated checkpoint"))?;
+        let finished = lines.next().ok_or_else(|| invalid("truncated checkpoint"))?;
+        let pixels = lines.next().ok_or_else(|| invalid("truncated checkpoint"))?;
+        if magic != MAGIC.as_bytes() {
+            return Err(invalid("not a checkpoint file"));
+        }
+
+        let mut checkpoint = Checkpoint::new(config);
+        if saved_fingerprint != checkpoint.fingerprint.as_bytes() {
+            return Err(invalid("the checkpoint was made with a different configuration"));
+        }
+        if finished.len() != checkpoint.finished_tiles.len() || pixels.len() != PIXEL_BYTES * checkpoint.color_buffer.len() {
+            return Err(invalid("truncated checkpoint"));
+        }
+        for (tile, &flag) in checkpoint.finished_tiles.iter_mut().zip(finished) {
+            *tile = flag == b'1';
+        }
+        for (color, bytes) in checkpoint.color_buffer.iter_mut().zip(pixels.chunks(PIXEL_BYTES)) {
+            let channel = |i: usize| f64::from_le_bytes(bytes[8*i..8*i + 8].try_into().unwrap()) as Real;
+            *color = Color::rgba(channel(0), channel(1), channel(2), channel(3));
+        }
+        Ok(checkpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use super::super::{render_mandelbrot, render_mandelbrot_resumable, tile_descryptors};
+
+    fn small_render() -> RenderConfig {
+        let mut config = RenderConfig::new();
+        config.width = 96;
+        config.height = 80;
+        config.tile_size = 32;
+        config.sample_count = 2;
+        config.seed = Some(44);
+        config.progress = false;
+        config
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("mandelbrot_set_{}.checkpoint", name)).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn partial_checkpoints_survive_a_save() {
+        let config = small_render();
+        let mut checkpoint = Checkpoint::new(&config);
+        let descryptors = tile_descryptors(&config);
+        let pixels = vec![Color::rgba(0.25, -1.5, 3.0, 0.5); 32 * 32];
+        checkpoint.store_tile(1, &descryptors[1], &pixels, &config.output_region());
+        checkpoint.store_tile(4, &descryptors[4], &pixels, &config.output_region());
+
+        let path = temp_path("partial_checkpoints_survive_a_save");
+        checkpoint.save(&path).unwrap();
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        let loaded = Checkpoint::load(&path, &config).unwrap();
+        assert_eq!(loaded.finished_tiles, checkpoint.finished_tiles);
+        assert_eq!(loaded.finished_tiles.iter().filter(|&&finished| finished).count(), 2);
+        assert_eq!(loaded.color_buffer, checkpoint.color_buffer);
+        assert!(!loaded.is_complete());
+
+        // Saving again replaces the previous checkpoint
+        checkpoint.finished_tiles = vec![true; checkpoint.finished_tiles.len()];
+        checkpoint.save(&path).unwrap();
+        assert!(Checkpoint::load(&path, &config).unwrap().is_complete());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn resumed_renders_match_a_straight_render() {
+        let config = small_render();
+        let full = render_mandelbrot(&config);
+        let region = config.output_region();
+
+        // Half of the tiles come from a saved checkpoint, the resume renders only the others
+        let mut checkpoint = Checkpoint::new(&config);
+        let descryptors = tile_descryptors(&config);
+        let mut stored_pixels = 0;
+        for (id, desc) in descryptors.iter().enumerate().filter(|(id, _)| id % 2 == 0) {
+            let pixels: Vec<Color> = (0..desc.thread_size.y).flat_map(|y| {
+                let start = (desc.offset.y + y) * region.width + desc.offset.x;
+                full[start..start + desc.thread_size.x].to_vec()
+            }).collect();
+            stored_pixels += pixels.len();
+            checkpoint.store_tile(id, desc, &pixels, &region);
+        }
+        let path = temp_path("resumed_renders_match_a_straight_render");
+        checkpoint.save(&path).unwrap();
+        let mut checkpoint = Checkpoint::load(&path, &config).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut saves = 0;
+        let stats = render_mandelbrot_resumable(&config, &AtomicBool::new(false), &mut checkpoint, |_| saves += 1);
+        assert!(checkpoint.is_complete());
+        assert_eq!(stats.pixels, region.width * region.height - stored_pixels);
+        // A finished render has nothing left to save
+        assert_eq!(saves, 0);
+        assert_eq!(checkpoint.color_buffer, full);
+    }
+
+    #[test]
+    fn checkpoints_of_other_configs_are_refused() {
+        let config = small_render();
+        let path = temp_path("checkpoints_of_other_configs_are_refused");
+        Checkpoint::new(&config).save(&path).unwrap();
+
+        // The thread count and the progress output don't change the pixels
+        let mut same = config.clone();
+        same.thread_count = 3;
+        same.progress = true;
+        assert!(Checkpoint::load(&path, &same).is_ok());
+        let mut other = config.clone();
+        other.max_iterations += 1;
+        let mut moved = config.clone();
+        moved.center.x += 0.001;
+        for changed in [other, moved] {
+            let error = Checkpoint::load(&path, &changed).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("different configuration"), "{}", error);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_and_corrupt_checkpoints_are_refused() {
+        let config = small_render();
+        let path = temp_path("truncated_and_corrupt_checkpoints_are_refused");
+        Checkpoint::new(&config).save(&path).unwrap();
+        let data = fs::read(&path).unwrap();
+        // The end of the third header line, the list of finished tiles
+        let finished_line = data.iter().enumerate().filter(|(_, &byte)| byte == b'\n').nth(2).unwrap().0;
+        assert_eq!(data.len() - finished_line - 1, PIXEL_BYTES * config.width * config.height);
+
+        let mut corrupt_magic = data.clone();
+        corrupt_magic[0] = b'M';
+        let mut short_tiles = data.clone();
+        short_tiles.remove(finished_line - 1);
+        for (name, bytes) in [("missing pixels", &data[..data.len() - 1]), ("header only", &data[..finished_line + 1]),
+            ("cut in the header", &data[..10]), ("empty", &data[..0]), ("wrong magic", &corrupt_magic[..]), ("short tile list", &short_tiles[..])] {
+            fs::write(&path, bytes).unwrap();
+            let error = Checkpoint::load(&path, &config).err().unwrap_or_else(|| panic!("{} loaded", name));
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", name);
+        }
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path, &config).err().unwrap().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,192 @@
+// Render recipes in TOML: every key is a command line flag without its dashes, so
+// max_iterations = 500 is --max-iterations 500. Only top level keys are supported
+use std::fs;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// Values keep the text they were written in, so numbers lose no digits on their way to the flag
+enum Value {
+    Text(String),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+// Skips spaces, and newlines and comments too if lines
+fn skip_blank(chars: &mut Peekable<Chars>, lines: bool) {
+    while let Some(&c) = chars.peek() {
+        if c == '#' && lines {
+            while chars.peek().is_some_and(|&c| c != '\n') {
+                chars.next();
+            }
+        }
+        else if c == ' ' || c == '\t' || c == '\r' || (c == '\n' && lines) {
+            chars.next();
+        }
+        else {
+            break;
+        }
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let quote = chars.next().unwrap();
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == quote => return Ok(string),
+            // Literal strings in single quotes have no escapes
+            Some('\\') if quote == '"' => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some(c @ ('"' | '\\')) => string.push(c),
+                _ => return Err(String::from("invalid escape in string")),
+            },
+            Some('\n') | None => return Err(String::from("unterminated string")),
+            Some(c) => string.push(c),
+        }
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    match chars.peek() {
+        Some('"') | Some('\'') => Ok(Value::Text(parse_string(chars)?)),
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            loop {
+                skip_blank(chars, true);
+                if chars.peek() == Some(&']') {
+                    chars.next();
+                    return Ok(Value::Array(items));
+                }
+                items.push(parse_value(chars)?);
+                skip_blank(chars, true);
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Value::Array(items)),
+                    _ => return Err(String::from("expected ',' or ']' in array")),
+                }
+            }
+        }
+        _ => {
+            let mut text = String::new();
+            while chars.peek().is_some_and(|&c| !c.is_whitespace() && !",]#".contains(c)) {
+                text.push(chars.next().unwrap());
+            }
+            match text.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                // Numbers, with the digit separators TOML allows dropped
+                _ if text.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)) => Ok(Value::Text(text.replace('_', ""))),
+                _ => Err(format!("invalid value '{}'", text)),
+            }
+        }
+    }
+}
+
+// Command line arguments of a value, arrays become comma separated lists
+fn flag_value(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::Text(text) => Ok(text.clone()),
+        Value::Array(items) => {
+            let items = items.iter().map(|item| match item {
+                Value::Array(_) => Err(format!("{} can't hold nested arrays", key)),
+                item => flag_value(key, item),
+            });
+            Ok(items.collect::<Result<Vec<String>, String>>()?.join(","))
+        }
+        Value::Bool(_) => Err(format!("{} can't hold booleans", key)),
+    }
+}
+
+// Parses the text of a config file into the command line flags it stands for
+fn parse_flags(text: &str) -> Result<Vec<String>, String> {
+    let mut chars = text.chars().peekable();
+    let mut keys: Vec<String> = Vec::new();
+    let mut flags = Vec::new();
+    loop {
+        skip_blank(&mut chars, true);
+        let key: String = match chars.peek() {
+            None => return Ok(flags),
+            Some('[') => return Err(String::from("tables aren't supported, every key has to be at the top level")),
+            Some('"') | Some('\'') => parse_string(&mut chars)?,
+            _ => {
+                let mut key = String::new();
+                while chars.peek().is_some_and(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    key.push(chars.next().unwrap());
+                }
+                key
+            }
+        };
+        let key = key.replace('_', "-");
+        if key.is_empty() {
+            return Err(String::from("expected a key"));
+        }
+        if key == "config" {
+            return Err(String::from("config files can't include other config files"));
+        }
+        if keys.contains(&key) {
+            return Err(format!("{} is set twice", key));
+        }
+        skip_blank(&mut chars, false);
+        if chars.next() != Some('=') {
+            return Err(format!("expected '=' after {}", key));
+        }
+        skip_blank(&mut chars, false);
+        let value = parse_value(&mut chars).map_err(|e| format!("{}: {}", key, e))?;
+        skip_blank(&mut chars, false);
+        if chars.peek().is_some_and(|&c| c != '\n' && c != '#') {
+            return Err(format!("expected a new line after the value of {}", key));
+        }
+
+        let flag = format!("--{}", key);
+        match value {
+            Value::Bool(true) => flags.push(flag),
+            Value::Bool(false) => {}
+            value => {
+                let value = flag_value(&key, &value)?;
+                flags.push(flag);
+                flags.push(value);
+            }
+        }
+        keys.push(key);
+    }
+}
+
+// Loads the flags of the config file at path, such as
+//   width = 1920
+//   center = ["-0.7453", 0.1127]
+//   deep_zoom = true
+pub fn load_flags(path: &str) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_flags(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_become_flags() {
+        let flags = parse_flags(r#"
+            # Seahorse valley
+            width = 1_920
+            center = ["-0.74530000000000000001", 0.1127]  # every digit is kept
+            max_iterations = 500
+            mode = 'burning-ship'
+            palette-hsv = [
+                200, 0.8,
+                1, 16,
+            ]
+            deep_zoom = true
+            gamma = false
+        "#).unwrap();
+        let expected = ["--width", "1920", "--center", "-0.74530000000000000001,0.1127", "--max-iterations", "500",
+            "--mode", "burning-ship", "--palette-hsv", "200,0.8,1,16", "--deep-zoom"];
+        assert_eq!(flags, expected);
+
+        for text in ["[render]\nwidth = 1", "width 1", "width = 1 2", "width = one", "width = 1\nwidth = 2", "center = [[1, 2]]", "out = \"image.png", "config = \"other.toml\""] {
+            assert!(parse_flags(text).is_err(), "{}", text);
+        }
+    }
+}
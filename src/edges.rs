@@ -0,0 +1,99 @@
+// Line art of the fractal: a Sobel filter over the escape counts of the pixel centers
+// marks where neighboring pixels escape at very different times
+use super::{Color, RenderConfig, Real, clamp, escape_level, map_pixel_centers};
+
+#[derive(Copy, Clone, Debug)]
+pub struct EdgeStyle {
+    pub line: Color,
+    pub background: Color,
+    // Gradient of ln(1 + iterations) drawn at full line strength, weaker gradients fade into the background
+    pub threshold: Real,
+}
+
+impl EdgeStyle {
+    pub fn new() -> EdgeStyle {
+        EdgeStyle {
+            line: Color::rgb(0.0, 0.0, 0.0),
+            background: Color::rgb(1.0, 1.0, 1.0),
+            threshold: 1.0,
+        }
+    }
+}
+
+impl Default for EdgeStyle {
+    fn default() -> EdgeStyle {
+        EdgeStyle::new()
+    }
+}
+
+// Renders the edge map of config, only the center of every pixel is iterated so
+// lines are anti-aliased by rendering larger and downsampling
+pub fn render_edges(config: &RenderConfig, style: &EdgeStyle) -> Vec<Color> {
+    // Interior samples count as max_iterations so the boundary of the set is an edge too
+    let levels = map_pixel_centers(config, escape_level);
+    let region = config.output_region();
+    let strengths = edge_strengths(&levels, region.width, region.height, style.threshold);
+
+    // The colors are in the color space of the palette
+    let (line, background) = match config.decodes_srgb() {
+        true => (style.line.to_linear(), style.background.to_linear()),
+        false => (style.line, style.background),
+    };
+    strengths.iter().map(|&strength| Color::lerp(background, line, strength)).collect()
+}
+
+// Line strength in [0:1] of every pixel of levels, a width x height field in row major order
+fn edge_strengths(levels: &[Real], width: usize, height: usize, threshold: Real) -> Vec<Real> {
+    let (width, height) = (width as isize, height as isize);
+    // Pixels past the border repeat the border pixels
+    let level = |x: isize, y: isize| levels[(clamp(y, 0, height - 1) * width + clamp(x, 0, width - 1)) as usize];
+
+    let mut strengths = Vec::with_capacity(levels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let gx = level(x + 1, y - 1) + 2.0*level(x + 1, y) + level(x + 1, y + 1)
+                - level(x - 1, y - 1) - 2.0*level(x - 1, y) - level(x - 1, y + 1);
+            let gy = level(x - 1, y + 1) + 2.0*level(x, y + 1) + level(x + 1, y + 1)
+                - level(x - 1, y - 1) - 2.0*level(x, y - 1) - level(x + 1, y - 1);
+            strengths.push(clamp((gx*gx + gy*gy).sqrt() / threshold, 0.0, 1.0));
+        }
+    }
+    strengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Vec2;
+
+    #[test]
+    fn flat_fields_have_no_edges() {
+        let levels = vec![3.5; 6 * 4];
+        assert!(edge_strengths(&levels, 6, 4, 1.0).iter().all(|&strength| strength == 0.0));
+    }
+
+    #[test]
+    fn steps_are_edges_on_both_sides_of_the_boundary() {
+        // Levels step from 0 to 1 between columns 2 and 3, the Sobel gradient there is 4
+        let levels: Vec<Real> = (0..6 * 3).map(|i| if i % 6 < 3 { 0.0 } else { 1.0 }).collect();
+        let strengths = edge_strengths(&levels, 6, 3, 8.0);
+        for row in strengths.chunks(6) {
+            assert_eq!(row, [0.0, 0.0, 0.5, 0.5, 0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn single_pixel_wide_images_repeat_their_border() {
+        let levels = [0.0, 0.0, 1.0, 1.0, 1.0];
+        assert_eq!(edge_strengths(&levels, 1, 5, 8.0), [0.0, 0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(edge_strengths(&levels, 5, 1, 8.0), [0.0, 0.5, 0.5, 0.0, 0.0]);
+
+        let mut config = RenderConfig::new();
+        config.width = 1;
+        config.height = 7;
+        config.center = Vec2::<Real>{x: -0.75, y: 0.0};
+        config.set_zoom(3.0);
+        config.progress = false;
+        assert_eq!(render_edges(&config, &EdgeStyle::new()).len(), 7);
+    }
+}
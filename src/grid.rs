@@ -0,0 +1,221 @@
+// Coordinate grid drawn over a finished render: thin lines at round steps of the complex plane,
+// brighter axes through the origin and the coordinates of the lines in a 3x5 pixel font
+use super::{Color, Region, RenderConfig, Real};
+
+// Grid lines across each side of the view, the step is rounded to 1, 2 or 5 times a power of ten
+const GRID_LINES: f64 = 6.0;
+// Lines drawn along an axis at most, in case rounding of a huge or tiny view gives far more
+const MAX_LINES: i64 = 64;
+const LINE_OPACITY: Real = 0.35;
+const AXIS_OPACITY: Real = 0.85;
+const GRID_COLOR: Color = Color {r: 1.0, g: 1.0, b: 1.0, a: 1.0};
+// Drawn around every glyph pixel so labels stay readable over bright areas
+const OUTLINE_COLOR: Color = Color {r: 0.0, g: 0.0, b: 0.0, a: 1.0};
+// Image side in pixels per font pixel of the labels
+const PIXELS_PER_FONT_SCALE: usize = 400;
+
+// Rows of a glyph of the font from the top, the three low bits of every row are its pixels
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        'i' => [0b010, 0b000, 0b010, 0b010, 0b010],
+        _ => [0b000; 5],
+    }
+}
+
+// Smallest of 1, 2 or 5 times a power of ten giving at most about GRID_LINES lines over range
+fn grid_step(range: f64) -> f64 {
+    let raw = range / GRID_LINES;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    for factor in [1.0, 2.0, 5.0] {
+        if factor * magnitude >= raw {
+            return factor * magnitude;
+        }
+    }
+    10.0 * magnitude
+}
+
+// Coordinate of a grid line with as many decimals as the step has
+fn label(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let text = format!("{:.*}", decimals, value);
+    // Lines at the origin can land a rounding error off zero and print as -0.000
+    match text.chars().all(|c| "-0.".contains(c)) {
+        true => String::from("0"),
+        false => text,
+    }
+}
+
+// The render buffer addressed in pixels of the whole image, pixels outside the region are skipped
+struct Canvas<'a> {
+    buffer: &'a mut [Color],
+    region: Region,
+    scale: i64,
+}
+
+impl Canvas<'_> {
+    fn blend(&mut self, x: i64, y: i64, color: Color, opacity: Real) {
+        let (x, y) = (x - self.region.x as i64, y - self.region.y as i64);
+        if x < 0 || y < 0 || x >= self.region.width as i64 || y >= self.region.height as i64 {
+            return;
+        }
+        let pixel = &mut self.buffer[y as usize * self.region.width + x as usize];
+        *pixel = Color::lerp(*pixel, color, opacity);
+    }
+
+    fn text_width(&self, text: &str) -> i64 {
+        (text.chars().count() as i64 * 4 - 1) * self.scale
+    }
+
+    // Draws text with its top left corner at x, y, the outline first so it never covers a glyph
+    fn text(&mut self, x: i64, y: i64, text: &str) {
+        let scale = self.scale;
+        for (color, margin) in [(OUTLINE_COLOR, scale), (GRID_COLOR, 0)] {
+            for (index, c) in text.chars().enumerate() {
+                let left = x + index as i64 * 4 * scale;
+                for (row, bits) in glyph(c).iter().enumerate() {
+                    for column in 0..3 {
+                        if bits & (0b100 >> column) == 0 {
+                            continue;
+                        }
+                        let (px, py) = (left + column * scale, y + row as i64 * scale);
+                        for dy in -margin..scale + margin {
+                            for dx in -margin..scale + margin {
+                                self.blend(px + dx, py + dy, color, 1.0);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Draws the grid of the view of config over buffer, the rendered region of the image in row major order
+#[allow(clippy::unnecessary_cast)]
+pub fn draw_grid(buffer: &mut [Color], config: &RenderConfig) {
+    let (width, height) = (config.width as f64, config.height as f64);
+    let (center_x, center_y) = (config.center.x as f64, config.center.y as f64);
+    let (view_width, view_height) = (config.view_size.x as f64, config.view_size.y as f64);
+    // Same mapping as the pixels of the render, image rows count down the imaginary axis unless flipped
+    let down = if config.flip_y { -1.0 } else { 1.0 };
+    let column = |re: f64| ((re - center_x) / view_width * width + width / 2.0).floor() as i64;
+    let row = |im: f64| (down * (center_y - im) / view_height * height + height / 2.0).floor() as i64;
+
+    // Every axis has a step of its own, so stretched views don't get thousands of lines along the long side
+    let (step_x, step_y) = (grid_step(view_width), grid_step(view_height));
+    let lines = |center: f64, size: f64, step: f64| -> Vec<i64> {
+        let first = ((center - size / 2.0) / step).ceil() as i64;
+        let last = ((center + size / 2.0) / step).floor() as i64;
+        (first..=last.min(first.saturating_add(MAX_LINES - 1))).collect()
+    };
+    let columns = lines(center_x, view_width, step_x);
+    let rows = lines(center_y, view_height, step_y);
+
+    let scale = (config.width.min(config.height) / PIXELS_PER_FONT_SCALE).max(1) as i64;
+    let mut canvas = Canvas {buffer, region: config.output_region(), scale};
+    let (image_width, image_height) = (config.width as i64, config.height as i64);
+    for (lines, vertical) in [(&columns, true), (&rows, false)] {
+        for &line in lines {
+            let opacity = if line == 0 { AXIS_OPACITY } else { LINE_OPACITY };
+            let position = match vertical {
+                true => column(line as f64 * step_x),
+                false => row(line as f64 * step_y),
+            };
+            let length = if vertical { image_height } else { image_width };
+            for along in 0..length {
+                match vertical {
+                    true => canvas.blend(position, along, GRID_COLOR, opacity),
+                    false => canvas.blend(along, position, GRID_COLOR, opacity),
+                }
+            }
+        }
+    }
+
+    // Labels sit next to the axes, or along the bottom and left edges while the axis is out of view
+    let margin = 2 * scale;
+    let text_height = 5 * scale;
+    let axis_row = row(0.0);
+    let label_row = match (0..image_height).contains(&axis_row) {
+        true => axis_row + margin,
+        false => image_height - text_height - margin,
+    };
+    let label_row = label_row.clamp(scale, (image_height - text_height - scale).max(scale));
+    for &line in &columns {
+        let text = label(line as f64 * step_x, step_x);
+        canvas.text(column(line as f64 * step_x) + margin, label_row, &text);
+    }
+    let axis_column = column(0.0);
+    for &line in rows.iter().filter(|&&line| line != 0) {
+        let text = label(line as f64 * step_y, step_y) + "i";
+        let label_column = match (0..image_width).contains(&axis_column) {
+            true => axis_column + margin,
+            false => margin,
+        };
+        let label_column = label_column.clamp(scale, (image_width - canvas.text_width(&text) - scale).max(scale));
+        canvas.text(label_column, row(line as f64 * step_y) + margin, &text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Vec2;
+
+    #[test]
+    fn axes_cross_at_the_origin() {
+        let mut config = RenderConfig::new();
+        config.width = 81;
+        config.height = 81;
+        config.center = Vec2::<Real>{x: 0.0, y: 0.0};
+        config.set_zoom(4.0);
+        let black = Color::rgb(0.0, 0.0, 0.0);
+        let mut buffer = vec![black; 81 * 81];
+        draw_grid(&mut buffer, &config);
+        // Steps of 1 over [-2:2], the origin maps into pixel 40 of both axes
+        let at = |x: usize, y: usize| buffer[y*81 + x];
+        let axis = Color::lerp(black, GRID_COLOR, AXIS_OPACITY);
+        let line = Color::lerp(black, GRID_COLOR, LINE_OPACITY);
+        assert_eq!(at(40, 12), axis);
+        assert_eq!(at(12, 40), axis);
+        assert_eq!(at(60, 12), line);
+        assert_eq!(at(12, 20), line);
+        assert_eq!(at(50, 12), black);
+        assert!(buffer.contains(&GRID_COLOR));
+        assert_eq!(label(-0.5 + 0.5, 0.1), "0");
+        assert_eq!(label(-1.25, 0.05), "-1.25");
+        assert!((grid_step(0.3) - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stretched_views_get_a_step_per_axis() {
+        let mut config = RenderConfig::new();
+        config.width = 32;
+        config.height = 32;
+        config.set_bounds(-1000.0, 1000.0, -0.00001, 0.00001).unwrap();
+        let black = Color::rgb(0.0, 0.0, 0.0);
+        let mut buffer = vec![black; 32 * 32];
+        draw_grid(&mut buffer, &config);
+        // Steps of 500 and 5e-6 put a line about every 8 pixels each way, the axes through pixel 16
+        let line = Color::lerp(black, GRID_COLOR, LINE_OPACITY);
+        let axis = Color::lerp(black, GRID_COLOR, AXIS_OPACITY);
+        let at = |x: usize, y: usize| buffer[y*32 + x];
+        assert_eq!(at(16, 2), axis);
+        assert_eq!(at(2, 16), axis);
+        assert_eq!(at(8, 2), line);
+        assert_eq!(at(2, 24), line);
+        assert_eq!(at(4, 2), black);
+        assert_eq!(at(2, 4), black);
+    }
+}
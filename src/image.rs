@@ -0,0 +1,530 @@
+// Writing the color buffer to PNG, PPM and BMP files, animations to APNG files and
+// iteration counts to PFM files
+use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write, stdout};
+use super::{Color, Real, clamp, divide_roundup, with_path};
+
+// Bits per channel of the saved image
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BitDepth {
+    Eight,
+    // Big-endian u16 channels, avoids banding on smooth gradients
+    Sixteen,
+}
+
+impl BitDepth {
+    fn max_value(&self) -> Real {
+        match self {
+            BitDepth::Eight => 255.0,
+            BitDepth::Sixteen => 65535.0,
+        }
+    }
+}
+
+// Curve that maps the exposed linear channels into [0:1], the buffer of bright renders can go past 1
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMap {
+    // Channels past 1 are clipped
+    None,
+    // c / (1 + c), rolls highlights off gently but also darkens the midtones
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve, keeps more contrast than Reinhard
+    Aces,
+}
+
+impl ToneMap {
+    fn apply(&self, value: Real) -> Real {
+        match self {
+            ToneMap::None => value,
+            ToneMap::Reinhard => value / (1.0 + value),
+            ToneMap::Aces => clamp(value * (2.51 * value + 0.03) / (value * (2.43 * value + 0.59) + 0.14), 0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SaveOptions {
+    pub bit_depth: BitDepth,
+    // Encodes the linear buffer with the sRGB transfer function before quantization
+    pub gamma: bool,
+    // Writes a single luminance channel, a grayscale PNG or a PGM instead of a PPM
+    pub grayscale: bool,
+    // Adds an ordered dither below one quantization step so slow gradients don't band
+    pub dither: bool,
+    // Stops the linear channels are brightened by before the tone map, negative values darken them
+    pub exposure: Real,
+    pub tone_map: ToneMap,
+}
+
+impl SaveOptions {
+    pub fn new() -> SaveOptions {
+        SaveOptions {
+            bit_depth: BitDepth::Eight,
+            gamma: true,
+            grayscale: false,
+            dither: false,
+            exposure: 0.0,
+            tone_map: ToneMap::None,
+        }
+    }
+}
+
+impl Default for SaveOptions {
+    fn default() -> SaveOptions {
+        SaveOptions::new()
+    }
+}
+
+// Path that writes to stdout instead of a file
+pub const STDOUT_PATH: &str = "-";
+
+// Creates the file along with any missing parent directories
+fn create_file(path: &str) -> io::Result<File> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent).map_err(|e| with_path(path, e))?;
+    }
+    File::create(Path::new(path)).map_err(|e| with_path(path, e))
+}
+
+// Buffered writer to the file at path, or to stdout for STDOUT_PATH
+fn open_output(path: &str) -> io::Result<BufWriter<Box<dyn Write>>> {
+    if path == STDOUT_PATH {
+        return Ok(BufWriter::new(Box::new(stdout().lock())));
+    }
+    Ok(BufWriter::new(Box::new(create_file(path)?)))
+}
+
+// 8x8 Bayer matrix, every threshold appears once
+const BAYER_MATRIX: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+// Offset in (-0.5:0.5) quantization steps added to the channels of pixel x, y, 0 without dithering
+fn dither_offset(options: &SaveOptions, x: usize, y: usize) -> Real {
+    if !options.dither {
+        return 0.0;
+    }
+    (BAYER_MATRIX[y % 8][x % 8] as Real + 0.5) / 64.0 - 0.5
+}
+
+// Maps a channel to [0:max], values outside [0:1] are clamped instead of wrapping around
+fn quantize(value: Real, max: Real, offset: Real) -> u16 {
+    clamp((clamp(value, 0.0, 1.0) * max + offset).round(), 0.0, max) as u16
+}
+
+// Appends a channel at the given depth, 16-bit samples are big-endian as both PNG and PPM expect
+fn push_channel(data: &mut Vec<u8>, value: Real, depth: BitDepth, offset: Real) {
+    let sample = quantize(value, depth.max_value(), offset);
+    match depth {
+        BitDepth::Eight => data.push(sample as u8),
+        BitDepth::Sixteen => data.extend_from_slice(&sample.to_be_bytes()),
+    }
+}
+
+fn encode(color: &Color, options: &SaveOptions) -> Color {
+    let scale = (2.0 as Real).powf(options.exposure);
+    let tone = |value: Real| options.tone_map.apply(value * scale);
+    let color = Color::rgba(tone(color.r), tone(color.g), tone(color.b), color.a);
+    match options.gamma {
+        true => color.to_srgb(),
+        false => color,
+    }
+}
+
+// Channels of a pixel as written to the file, color_channels is RGB or RGBA
+fn channels(color: &Color, options: &SaveOptions, color_channels: usize) -> Vec<Real> {
+    if options.grayscale {
+        // Rec. 709 luminance, computed before the transfer function is applied
+        let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+        let gray = encode(&Color::rgba(luminance, luminance, luminance, color.a), options);
+        return vec![gray.r];
+    }
+    let color = encode(color, options);
+    vec![color.r, color.g, color.b, color.a][..color_channels].to_vec()
+}
+
+// Saves an 8-bit sRGB encoded RGBA PNG
+pub fn save_image(color_buffer: &[Color], width: usize, height: usize, path: &str) -> io::Result<()> {
+    save_png(color_buffer, width, height, path, &SaveOptions::new())
+}
+
+fn png_color_type(options: &SaveOptions) -> png::ColorType {
+    if options.grayscale { png::ColorType::Grayscale } else { png::ColorType::RGBA }
+}
+
+fn png_bit_depth(options: &SaveOptions) -> png::BitDepth {
+    match options.bit_depth {
+        BitDepth::Eight => png::BitDepth::Eight,
+        BitDepth::Sixteen => png::BitDepth::Sixteen,
+    }
+}
+
+// Unfiltered PNG samples of the buffer, row after row
+fn png_data(color_buffer: &[Color], width: usize, height: usize, options: &SaveOptions) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 * width * height);
+    for (i, color) in color_buffer.iter().take(width * height).enumerate() {
+        let offset = dither_offset(options, i % width, i / width);
+        for channel in channels(color, options, 4) {
+            push_channel(&mut data, channel, options.bit_depth, offset);
+        }
+    }
+    data
+}
+
+// Tightly packed 8-bit RGBA of the buffer, the pixels save_image writes. gamma encodes
+// a linear buffer to sRGB, as rendered with RenderConfig::gamma
+pub fn to_rgba8(color_buffer: &[Color], width: usize, height: usize, gamma: bool) -> Vec<u8> {
+    png_data(color_buffer, width, height, &SaveOptions {gamma, ..SaveOptions::new()})
+}
+
+pub fn save_png(color_buffer: &[Color], width: usize, height: usize, path: &str, options: &SaveOptions) -> io::Result<()> {
+    let mut w = open_output(path)?;
+
+    let mut encoder = png::Encoder::new(&mut w, width as u32, height as u32);
+    encoder.set_color(png_color_type(options));
+    encoder.set_depth(png_bit_depth(options));
+    let mut writer = encoder.write_header().map_err(|e| with_path(path, e.into()))?;
+
+    let rgba_data = png_data(color_buffer, width, height, options);
+    writer.write_image_data(&rgba_data).map_err(|e| with_path(path, e.into()))?;
+    // Errors of the final flush would be lost when the writers are dropped
+    drop(writer);
+    w.flush().map_err(|e| with_path(path, e))
+}
+
+// Animated PNG built frame by frame, frames are kept compressed until the file is saved
+pub struct Apng {
+    width: usize,
+    height: usize,
+    // Display time of every frame in milliseconds
+    delay: u16,
+    options: SaveOptions,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Apng {
+    pub fn new(width: usize, height: usize, delay: u16, options: &SaveOptions) -> Apng {
+        Apng {width, height, delay, options: *options, frames: Vec::new()}
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn delay(&self) -> u16 {
+        self.delay
+    }
+
+    pub fn add_frame(&mut self, color_buffer: &[Color]) {
+        let data = png_data(color_buffer, self.width, self.height, &self.options);
+        let row_length = data.len() / self.height.max(1);
+        // Every row starts with its filter type, 0 leaves the row unfiltered
+        let mut filtered = Vec::with_capacity(data.len() + self.height);
+        for row in data.chunks(row_length.max(1)) {
+            filtered.push(0);
+            filtered.extend_from_slice(row);
+        }
+        self.frames.push(deflate::deflate_bytes_zlib(&filtered));
+    }
+
+    // Writes every frame added so far, the animation loops forever
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        // Without an IDAT chunk the file would not be a PNG at all
+        if self.frames.is_empty() {
+            return Err(with_path(path, io::Error::new(io::ErrorKind::InvalidInput, "an animation needs at least one frame")));
+        }
+        let mut w = open_output(path)?;
+
+        let mut encoder = png::Encoder::new(&mut w, self.width as u32, self.height as u32);
+        encoder.set_color(png_color_type(&self.options));
+        encoder.set_depth(png_bit_depth(&self.options));
+        let mut writer = encoder.write_header().map_err(|e| with_path(path, e.into()))?;
+
+        let mut animation_control = Vec::new();
+        animation_control.extend_from_slice(&(self.frames.len() as u32).to_be_bytes());
+        animation_control.extend_from_slice(&0u32.to_be_bytes());
+        writer.write_chunk(*b"acTL", &animation_control).map_err(|e| with_path(path, e.into()))?;
+
+        // fcTL and fdAT chunks share one sequence number counter
+        let mut sequence: u32 = 0;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let mut frame_control = Vec::new();
+            for value in &[sequence, self.width as u32, self.height as u32, 0, 0] {
+                frame_control.extend_from_slice(&value.to_be_bytes());
+            }
+            frame_control.extend_from_slice(&self.delay.to_be_bytes());
+            frame_control.extend_from_slice(&1000u16.to_be_bytes());
+            // No disposal, the frame replaces the whole canvas
+            frame_control.extend_from_slice(&[0, 0]);
+            writer.write_chunk(*b"fcTL", &frame_control).map_err(|e| with_path(path, e.into()))?;
+            sequence += 1;
+
+            // The first frame doubles as the still image shown by viewers without APNG support
+            if i == 0 {
+                writer.write_chunk(*b"IDAT", frame).map_err(|e| with_path(path, e.into()))?;
+            }
+            else {
+                let mut frame_data = sequence.to_be_bytes().to_vec();
+                frame_data.extend_from_slice(frame);
+                writer.write_chunk(*b"fdAT", &frame_data).map_err(|e| with_path(path, e.into()))?;
+                sequence += 1;
+            }
+        }
+
+        drop(writer);
+        w.flush().map_err(|e| with_path(path, e))
+    }
+}
+
+// Writes a binary PPM (P6), or a PGM (P5) for grayscale, the alpha channel is dropped
+pub fn save_ppm(color_buffer: &[Color], width: usize, height: usize, path: &str, options: &SaveOptions) -> io::Result<()> {
+    let mut w = open_output(path)?;
+
+    let mut rgb_data = Vec::with_capacity(6 * width * height);
+    for (i, color) in color_buffer.iter().take(width * height).enumerate() {
+        let offset = dither_offset(options, i % width, i / width);
+        for channel in channels(color, options, 3) {
+            push_channel(&mut rgb_data, channel, options.bit_depth, offset);
+        }
+    }
+    let magic = if options.grayscale { "P5" } else { "P6" };
+    write!(w, "{}\n{} {}\n{}\n", magic, width, height, options.bit_depth.max_value()).map_err(|e| with_path(path, e))?;
+    w.write_all(&rgb_data).map_err(|e| with_path(path, e))?;
+    w.flush().map_err(|e| with_path(path, e))
+}
+
+// Writes an uncompressed 24-bit BMP, rows go from the bottom of the image to the top and are
+// padded to a multiple of 4 bytes. BMP has no 16-bit RGB format, the bit depth is ignored
+pub fn save_bmp(color_buffer: &[Color], width: usize, height: usize, path: &str, options: &SaveOptions) -> io::Result<()> {
+    let mut w = open_output(path)?;
+
+    const HEADER_SIZE: u32 = 14 + 40;
+    let row_size = divide_roundup(3 * width, 4) * 4;
+    let image_size = (row_size * height) as u32;
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(&(HEADER_SIZE + image_size).to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    // BITMAPINFOHEADER, a positive height means bottom-up rows
+    header.extend_from_slice(&40u32.to_le_bytes());
+    header.extend_from_slice(&(width as i32).to_le_bytes());
+    header.extend_from_slice(&(height as i32).to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&24u16.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.extend_from_slice(&image_size.to_le_bytes());
+    // 72 DPI in pixels per meter
+    header.extend_from_slice(&2835i32.to_le_bytes());
+    header.extend_from_slice(&2835i32.to_le_bytes());
+    header.extend_from_slice(&[0; 8]);
+    w.write_all(&header).map_err(|e| with_path(path, e))?;
+
+    let mut row_data = Vec::with_capacity(row_size);
+    for (y, row) in color_buffer[..width * height].chunks(width.max(1)).enumerate().rev() {
+        row_data.clear();
+        for (x, color) in row.iter().enumerate() {
+            let offset = dither_offset(options, x, y);
+            let rgb = match channels(color, options, 3).as_slice() {
+                [gray] => [*gray; 3],
+                rgb => [rgb[0], rgb[1], rgb[2]],
+            };
+            for channel in rgb.iter().rev() {
+                push_channel(&mut row_data, *channel, BitDepth::Eight, offset);
+            }
+        }
+        row_data.resize(row_size, 0);
+        w.write_all(&row_data).map_err(|e| with_path(path, e))?;
+    }
+    w.flush().map_err(|e| with_path(path, e))
+}
+
+// Saves the buffer in the format matching the path extension: .ppm, .bmp, or PNG for
+// anything else. STDOUT_PATH writes a PNG to stdout
+pub fn save_image_auto(color_buffer: &[Color], width: usize, height: usize, path: &str, options: &SaveOptions) -> io::Result<()> {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    if extension.eq_ignore_ascii_case("ppm") {
+        return save_ppm(color_buffer, width, height, path, options);
+    }
+    if extension.eq_ignore_ascii_case("bmp") {
+        return save_bmp(color_buffer, width, height, path, options);
+    }
+    save_png(color_buffer, width, height, path, options)
+}
+
+// Writes a grayscale Portable Float Map: a "Pf" header followed by little-endian f32
+// values, rows go from the bottom of the image to the top as the format requires
+pub fn save_iterations(iterations: &[f32], width: usize, height: usize, path: &str) -> io::Result<()> {
+    let mut w = open_output(path)?;
+
+    write!(w, "Pf\n{} {}\n-1.0\n", width, height).map_err(|e| with_path(path, e))?;
+    for row in iterations[..width * height].chunks(width.max(1)).rev() {
+        for value in row {
+            w.write_all(&value.to_le_bytes()).map_err(|e| with_path(path, e))?;
+        }
+    }
+    w.flush().map_err(|e| with_path(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_png(path: &str) -> Vec<u8> {
+        let mut decoder = png::Decoder::new(File::open(path).unwrap());
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut data = vec![0u8; info.buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn save_image_clamps_channels() {
+        let path = std::env::temp_dir().join("mandelbrot_set_save_image_clamps_channels.png");
+        let path = path.to_str().unwrap();
+        let color_buffer = [Color {r: 1.5, g: 0.999, b: -0.5, a: 1.0}];
+        save_image(&color_buffer, 1, 1, path).unwrap();
+        assert_eq!(decode_png(path), [255, 255, 0, 255]);
+    }
+
+    #[test]
+    fn to_rgba8_matches_the_saved_image() {
+        let path = std::env::temp_dir().join("mandelbrot_set_to_rgba8.png");
+        let path = path.to_str().unwrap();
+        let color_buffer = [Color::rgb(0.2, 0.5, 1.0), Color::rgba(0.0, 0.04, 0.9, 0.5)];
+        save_image(&color_buffer, 2, 1, path).unwrap();
+        assert_eq!(to_rgba8(&color_buffer, 2, 1, true), decode_png(path));
+    }
+
+    #[test]
+    fn save_png_writes_big_endian_sixteen_bit_channels() {
+        let path = std::env::temp_dir().join("mandelbrot_set_save_png_sixteen_bit.png");
+        let path = path.to_str().unwrap();
+        let color_buffer = [Color {r: 1.5, g: 0.5, b: 0.0, a: 1.0}];
+        let options = SaveOptions {bit_depth: BitDepth::Sixteen, gamma: false, grayscale: false, dither: false, ..SaveOptions::new()};
+        save_png(&color_buffer, 1, 1, path, &options).unwrap();
+        assert_eq!(decode_png(path), [0xff, 0xff, 0x80, 0x00, 0x00, 0x00, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn save_bmp_pads_rows_bottom_up() {
+        let path = std::env::temp_dir().join("mandelbrot_set_save_bmp_pads_rows.bmp");
+        let path = path.to_str().unwrap();
+        let red = Color::rgb(1.0, 0.0, 0.0);
+        let blue = Color::rgb(0.0, 0.0, 1.0);
+        let options = SaveOptions {bit_depth: BitDepth::Eight, gamma: false, grayscale: false, dither: false, ..SaveOptions::new()};
+        save_bmp(&[red, red, blue, blue], 2, 2, path, &options).unwrap();
+        let data = fs::read(path).unwrap();
+        // 2 pixels of 3 bytes are padded to 8 bytes per row, the blue bottom row comes first
+        assert_eq!(data.len(), 54 + 2 * 8);
+        assert_eq!(&data[2..6], &70u32.to_le_bytes());
+        assert_eq!(&data[54..], &[255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 255, 0, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn dither_keeps_the_mean_level_of_a_flat_area() {
+        // A quarter of a step above 0 rounds to 1 for a quarter of the Bayer thresholds
+        let options = SaveOptions {bit_depth: BitDepth::Eight, gamma: false, grayscale: true, dither: true, ..SaveOptions::new()};
+        let color_buffer = vec![Color::rgb(0.25 / 255.0, 0.25 / 255.0, 0.25 / 255.0); 64];
+        let data = png_data(&color_buffer, 8, 8, &options);
+        assert_eq!(data.iter().filter(|&&sample| sample == 1).count(), 16);
+        assert!(data.iter().all(|&sample| sample <= 1));
+    }
+
+    #[test]
+    fn tone_maps_roll_off_the_exposed_highlights() {
+        let colors: Vec<Color> = [0.0, 0.25, 0.5, 1.0, 4.0, 100.0].iter().map(|&value| Color::rgb(value, value, value)).collect();
+        let exposed = SaveOptions {exposure: 1.0, gamma: false, grayscale: true, ..SaveOptions::new()};
+        assert_eq!(png_data(&colors, 6, 1, &exposed), [0, 128, 255, 255, 255, 255]);
+        // Brighter channels stay brighter instead of all clipping to white
+        let reinhard = png_data(&colors, 6, 1, &SaveOptions {tone_map: ToneMap::Reinhard, ..exposed});
+        assert!(reinhard.windows(2).all(|pair| pair[0] < pair[1]) && reinhard[5] < 255, "{:?}", reinhard);
+        let aces = png_data(&colors, 6, 1, &SaveOptions {tone_map: ToneMap::Aces, ..exposed});
+        assert!(aces[..5].windows(2).all(|pair| pair[0] < pair[1]) && aces[5] == 255, "{:?}", aces);
+    }
+
+    #[test]
+    fn save_image_creates_missing_directories() {
+        let root = std::env::temp_dir().join("mandelbrot_set_save_image_creates_missing_directories");
+        let _ = fs::remove_dir_all(&root);
+        let path = root.join("nested").join("dir").join("image.png");
+        let color_buffer = [Color::new(); 4];
+        save_image(&color_buffer, 2, 2, path.to_str().unwrap()).unwrap();
+        assert!(path.is_file());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn apng_frames_follow_the_sequence_numbers() {
+        let path = std::env::temp_dir().join("mandelbrot_set_apng_frames.png");
+        let path = path.to_str().unwrap();
+        let options = SaveOptions {bit_depth: BitDepth::Eight, gamma: false, grayscale: false, dither: false, ..SaveOptions::new()};
+        let frames = [Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 1.0, 0.0), Color::rgb(0.0, 0.0, 1.0)];
+        let mut apng = Apng::new(2, 2, 40, &options);
+        for &color in &frames {
+            apng.add_frame(&[color; 4]);
+        }
+        assert_eq!(apng.frame_count(), 3);
+        apng.save(path).unwrap();
+
+        // Chunks after the 8 byte signature are a length, a type, the data and a CRC
+        let data = fs::read(path).unwrap();
+        let mut chunks: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut offset = 8;
+        while offset < data.len() {
+            let length = u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize;
+            let kind = String::from_utf8(data[offset + 4..offset + 8].to_vec()).unwrap();
+            chunks.push((kind, data[offset + 8..offset + 8 + length].to_vec()));
+            offset += 12 + length;
+        }
+        assert_eq!(offset, data.len());
+        let kinds: Vec<&str> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]);
+
+        let be_u32 = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        // 3 frames looping forever
+        assert_eq!(be_u32(&chunks[1].1[0..4]), 3);
+        assert_eq!(be_u32(&chunks[1].1[4..8]), 0);
+        let sequence: Vec<u32> = chunks.iter().filter(|(kind, _)| kind == "fcTL" || kind == "fdAT").map(|(_, data)| be_u32(data)).collect();
+        assert_eq!(sequence, [0, 1, 2, 3, 4]);
+        for (_, frame_control) in chunks.iter().filter(|(kind, _)| kind == "fcTL") {
+            assert_eq!(frame_control.len(), 26);
+            assert_eq!(be_u32(&frame_control[4..8]), 2);
+            assert_eq!(be_u32(&frame_control[8..12]), 2);
+            // 40 / 1000 seconds
+            assert_eq!(&frame_control[20..24], &[0, 40, 0x03, 0xe8]);
+        }
+
+        // The decoder checks the CRCs and reads the fdAT frames after the still image
+        let mut decoder = png::Decoder::new(File::open(path).unwrap());
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        assert_eq!(reader.info().animation_control.map(|control| control.num_frames), Some(3));
+        let mut pixels = vec![0u8; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, [255, 0, 0, 255].repeat(4));
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, [0, 255, 0, 255].repeat(4));
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, [0, 0, 255, 255].repeat(4));
+    }
+
+    #[test]
+    fn empty_animations_are_refused() {
+        let path = std::env::temp_dir().join("mandelbrot_set_empty_animation.png");
+        let _ = fs::remove_file(&path);
+        let apng = Apng::new(2, 2, 40, &SaveOptions::new());
+        let error = apng.save(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,206 @@
+// Interactive exploration in the terminal: a low resolution preview of the view is
+// drawn with true-color half blocks and steered with commands typed on stdin
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use mandelbrot_set::{Color, RenderConfig, Real, render_mandelbrot};
+
+// Every terminal cell shows two preview pixels stacked vertically
+const PREVIEW_WIDTH: usize = 80;
+const PREVIEW_HEIGHT: usize = 48;
+// Share of the view a pan command moves by
+const PAN_STEP: Real = 0.25;
+const ZOOM_STEP: Real = 2.0;
+
+const HELP: &str = "w/a/s/d pan, + / - zoom, c <column> <row> recenter on a cell, r render the full image, q quit";
+
+fn channel(value: Real) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn draw(preview: &RenderConfig, color_buffer: &[Color]) -> io::Result<()> {
+    let width = preview.width;
+    // Clear the screen, the progress bar of the render is overwritten
+    let mut screen = String::from("\x1b[H\x1b[2J");
+    for y in (0..preview.height).step_by(2) {
+        for x in 0..width {
+            let mut top = color_buffer[y * width + x];
+            let mut bottom = if y + 1 < preview.height { color_buffer[(y + 1) * width + x] } else { Color::new() };
+            if preview.gamma {
+                top = top.to_srgb();
+                bottom = bottom.to_srgb();
+            }
+            let _ = write!(screen, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                channel(top.r), channel(top.g), channel(top.b), channel(bottom.r), channel(bottom.g), channel(bottom.b));
+        }
+        screen.push_str("\x1b[0m\n");
+    }
+    let _ = write!(screen, "center {},{}  zoom {}\n{}\n> ", preview.center.x, preview.center.y, preview.view_size.y, HELP);
+    // print! would panic once the terminal or pipe is gone
+    let mut stdout = io::stdout();
+    stdout.write_all(screen.as_bytes())?;
+    stdout.flush()
+}
+
+// Low resolution config of the view of config the preview is drawn with
+fn preview_config(config: &RenderConfig) -> RenderConfig {
+    let mut preview = config.clone();
+    preview.width = PREVIEW_WIDTH;
+    preview.height = PREVIEW_HEIGHT;
+    preview.sample_count = 1;
+    preview.progress = false;
+    preview.region = None;
+    preview.set_zoom(config.view_size.y);
+    preview
+}
+
+// The view of preview at the resolution and settings of config
+fn full_view(config: &RenderConfig, preview: &RenderConfig) -> RenderConfig {
+    let mut full = config.clone();
+    full.center = preview.center;
+    full.set_zoom(preview.view_size.y);
+    full
+}
+
+// Applies one line of commands to preview, render is called with the preview of every r
+// command the moment it is reached, false once the line quits
+fn apply_commands(line: &str, preview: &mut RenderConfig, mut render: impl FnMut(&RenderConfig)) -> bool {
+    // Screen up in the complex plane, down the imaginary axis for flip_y
+    let up = if preview.flip_y { -preview.view_size.y } else { preview.view_size.y };
+    let mut words = line.split_whitespace();
+    if line.trim_start().starts_with('c') {
+        words.next();
+        let column = words.next().and_then(|w| w.parse::<usize>().ok());
+        let row = words.next().and_then(|w| w.parse::<usize>().ok());
+        if let (Some(column), Some(row)) = (column, row) {
+            // A cell covers one preview pixel horizontally and two vertically
+            let x = (column as Real + 0.5) / (PREVIEW_WIDTH as Real) - 0.5;
+            let y = (2.0 * row as Real + 1.0) / (PREVIEW_HEIGHT as Real) - 0.5;
+            preview.center.x += x * preview.view_size.x;
+            preview.center.y -= y * up;
+        }
+        return true;
+    }
+
+    // Every other character is a command of its own, "ww++" pans twice and zooms in twice
+    for command in line.chars() {
+        match command {
+            'w' => preview.center.y += up * PAN_STEP,
+            's' => preview.center.y -= up * PAN_STEP,
+            'a' => preview.center.x -= preview.view_size.x * PAN_STEP,
+            'd' => preview.center.x += preview.view_size.x * PAN_STEP,
+            '+' => preview.set_zoom(preview.view_size.y / ZOOM_STEP),
+            '-' => preview.set_zoom(preview.view_size.y * ZOOM_STEP),
+            'r' => render(preview),
+            'q' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+// Runs the preview loop, render is called with the full resolution config of the current view
+pub fn run(config: &RenderConfig, render: impl Fn(&RenderConfig)) {
+    let mut preview = preview_config(config);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if draw(&preview, &render_mandelbrot(&preview)).is_err() {
+            return;
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return,
+        };
+        let running = apply_commands(&line, &mut preview, |view| {
+            render(&full_view(config, view));
+            let _ = writeln!(io::stdout(), "Press enter to continue");
+            let _ = lines.next();
+        });
+        if !running {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mandelbrot_set::Vec2;
+
+    fn view(flip_y: bool) -> RenderConfig {
+        let mut config = RenderConfig::new();
+        config.width = 640;
+        config.height = 480;
+        config.sample_count = 4;
+        config.center = Vec2::<Real>{x: -0.5, y: 0.25};
+        config.set_zoom(2.0);
+        config.flip_y = flip_y;
+        preview_config(&config)
+    }
+
+    fn assert_close(value: Real, expected: Real) {
+        assert!((value - expected).abs() < 1e-5, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn commands_pan_and_zoom_the_preview() {
+        let mut preview = view(false);
+        let view_width = preview.view_size.x;
+        assert!(apply_commands("wd", &mut preview, |_| panic!("nothing to render")));
+        assert_close(preview.center.x, -0.5 + view_width * PAN_STEP);
+        assert_close(preview.center.y, 0.25 + 2.0 * PAN_STEP);
+        assert!(apply_commands("++-", &mut preview, |_| {}));
+        assert_close(preview.view_size.y, 1.0);
+        assert!(apply_commands("s", &mut preview, |_| {}));
+        assert_close(preview.center.y, 0.25 + 2.0 * PAN_STEP - PAN_STEP);
+
+        // Up on the screen is down the imaginary axis
+        let mut flipped = view(true);
+        apply_commands("w", &mut flipped, |_| {});
+        assert_close(flipped.center.y, 0.25 - 2.0 * PAN_STEP);
+        assert!(!apply_commands("dq+", &mut flipped, |_| {}));
+        assert_close(flipped.view_size.y, 2.0);
+    }
+
+    #[test]
+    fn cells_recenter_on_their_middle() {
+        // The top left cell is one preview pixel wide and two high
+        let mut preview = view(false);
+        let view_width = preview.view_size.x;
+        apply_commands("c 0 0", &mut preview, |_| {});
+        assert_close(preview.center.x, -0.5 - view_width / 2.0 + view_width * 0.5 / PREVIEW_WIDTH as Real);
+        assert_close(preview.center.y, 0.25 + 1.0 - 2.0 / PREVIEW_HEIGHT as Real);
+
+        let mut flipped = view(true);
+        apply_commands("c 0 0", &mut flipped, |_| {});
+        assert_close(flipped.center.y, 0.25 - 1.0 + 2.0 / PREVIEW_HEIGHT as Real);
+
+        // The middle of the screen keeps the view, incomplete commands are ignored
+        let mut preview = view(false);
+        apply_commands("c 40", &mut preview, |_| {});
+        apply_commands("c x 12", &mut preview, |_| {});
+        assert_close(preview.center.x, -0.5);
+        assert_close(preview.center.y, 0.25);
+    }
+
+    #[test]
+    fn renders_keep_the_full_resolution() {
+        let mut config = RenderConfig::new();
+        config.width = 640;
+        config.height = 480;
+        config.sample_count = 4;
+        let mut preview = preview_config(&config);
+        assert_eq!((preview.width, preview.height, preview.sample_count), (PREVIEW_WIDTH, PREVIEW_HEIGHT, 1));
+
+        let mut rendered = Vec::new();
+        apply_commands("+r d", &mut preview, |view| rendered.push(full_view(&config, view)));
+        assert_eq!(rendered.len(), 1);
+        let full = &rendered[0];
+        assert_eq!((full.width, full.height, full.sample_count), (640, 480, 4));
+        assert_close(full.view_size.y, config.view_size.y / ZOOM_STEP);
+        assert_close(full.view_size.x, full.view_size.y * 640.0 / 480.0);
+        assert_close(full.center.x, config.center.x);
+        assert!(preview.center.x > config.center.x);
+    }
+}
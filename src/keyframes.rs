@@ -0,0 +1,185 @@
+// Keyframed flythroughs: views at given times, loaded from a JSON file and interpolated in between
+use std::fs;
+use std::io;
+use std::iter::Peekable;
+use std::str::Chars;
+use super::{RenderConfig, Real, Vec2, interpolate_zoom, is_positive, with_path};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Keyframe {
+    pub time: Real,
+    pub center: Vec2<Real>,
+    // View height, like RenderConfig::set_zoom
+    pub zoom: Real,
+    pub palette_offset: Real,
+}
+
+impl Keyframe {
+    // Moves the view of config to the keyframe
+    pub fn apply(&self, config: &mut RenderConfig) {
+        config.center = self.center;
+        config.set_zoom(self.zoom);
+        config.palette_offset = self.palette_offset;
+    }
+}
+
+// The subset of JSON keyframe files use, strings only appear as object keys
+enum Value {
+    Number(Real),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err(String::from("expected a string"));
+    }
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some(c) => string.push(c),
+            None => return Err(String::from("unterminated string")),
+        }
+    }
+}
+
+// Parses the comma separated items of an array or object up to the closing character
+fn parse_items<T>(chars: &mut Peekable<Chars>, close: char, mut item: impl FnMut(&mut Peekable<Chars>) -> Result<T, String>) -> Result<Vec<T>, String> {
+    chars.next();
+    let mut items = Vec::new();
+    skip_whitespace(chars);
+    if chars.peek() == Some(&close) {
+        chars.next();
+        return Ok(items);
+    }
+    loop {
+        items.push(item(chars)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => {}
+            Some(c) if c == close => return Ok(items),
+            _ => return Err(format!("expected ',' or '{}'", close)),
+        }
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('[') => Ok(Value::Array(parse_items(chars, ']', parse_value)?)),
+        Some('{') => Ok(Value::Object(parse_items(chars, '}', |chars| {
+            skip_whitespace(chars);
+            let key = parse_string(chars)?;
+            skip_whitespace(chars);
+            if chars.next() != Some(':') {
+                return Err(format!("expected ':' after \"{}\"", key));
+            }
+            Ok((key, parse_value(chars)?))
+        })?)),
+        _ => {
+            let mut number = String::new();
+            while chars.peek().is_some_and(|&c| c.is_ascii_digit() || "+-.eE".contains(c)) {
+                number.push(chars.next().unwrap());
+            }
+            number.parse::<Real>().map(Value::Number).map_err(|_| format!("invalid number '{}'", number))
+        }
+    }
+}
+
+fn parse_keyframe(value: &Value) -> Result<Keyframe, String> {
+    let fields = match value {
+        Value::Object(fields) => fields,
+        _ => return Err(String::from("every keyframe must be an object")),
+    };
+    let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+    let number = |name: &str| match field(name) {
+        Some(Value::Number(number)) => Ok(*number),
+        _ => Err(format!("keyframe needs a number \"{}\"", name)),
+    };
+    let center = match field("center") {
+        Some(Value::Array(values)) => match values.as_slice() {
+            [Value::Number(x), Value::Number(y)] => Vec2::<Real>{x: *x, y: *y},
+            _ => return Err(String::from("keyframe \"center\" must be [x, y]")),
+        },
+        _ => return Err(String::from("keyframe needs a \"center\" [x, y]")),
+    };
+    let palette_offset = if field("palette_offset").is_some() { number("palette_offset")? } else { 0.0 };
+    Ok(Keyframe {time: number("time")?, center, zoom: number("zoom")?, palette_offset})
+}
+
+// Loads a JSON array of keyframes such as
+// [{"time": 0, "center": [-0.5, 0], "zoom": 3}, {"time": 10, "center": [-0.7453, 0.1127], "zoom": 1e-4, "palette_offset": 32}]
+// palette_offset defaults to 0 and the times have to increase
+pub fn load_keyframes(path: &str) -> io::Result<Vec<Keyframe>> {
+    let invalid = |message: String| with_path(path, io::Error::new(io::ErrorKind::InvalidData, message));
+    let text = fs::read_to_string(path).map_err(|e| with_path(path, e))?;
+
+    let mut chars = text.chars().peekable();
+    let keyframes = match parse_value(&mut chars).map_err(invalid)? {
+        Value::Array(values) => values.iter().map(parse_keyframe).collect::<Result<Vec<Keyframe>, String>>().map_err(invalid)?,
+        _ => return Err(invalid(String::from("expected an array of keyframes"))),
+    };
+    if keyframes.is_empty() {
+        return Err(invalid(String::from("expected at least one keyframe")));
+    }
+    for pair in keyframes.windows(2) {
+        if pair[1].time <= pair[0].time {
+            return Err(invalid(format!("keyframe times must increase, {} follows {}", pair[1].time, pair[0].time)));
+        }
+    }
+    if !keyframes.iter().all(|keyframe| is_positive(keyframe.zoom)) {
+        return Err(invalid(String::from("keyframe zooms must be positive")));
+    }
+    Ok(keyframes)
+}
+
+// View at time between the surrounding keyframes, the first or last one outside their range.
+// The center and palette offset move linearly and the zoom geometrically, see interpolate_zoom
+pub fn interpolate_keyframes(keyframes: &[Keyframe], time: Real) -> Keyframe {
+    let first = keyframes[0];
+    let last = keyframes[keyframes.len() - 1];
+    if time <= first.time {
+        return first;
+    }
+    if time >= last.time {
+        return last;
+    }
+    let next = keyframes.iter().position(|keyframe| keyframe.time > time).unwrap();
+    let (a, b) = (keyframes[next - 1], keyframes[next]);
+    let t = (time - a.time) / (b.time - a.time);
+    Keyframe {
+        time,
+        center: Vec2::<Real>{x: a.center.x + (b.center.x - a.center.x) * t, y: a.center.y + (b.center.y - a.center.y) * t},
+        zoom: interpolate_zoom(a.zoom, b.zoom, t),
+        palette_offset: a.palette_offset + (b.palette_offset - a.palette_offset) * t,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyframes_are_loaded_and_interpolated() {
+        let path = std::env::temp_dir().join("mandelbrot_set_keyframes.json");
+        let path = path.to_str().unwrap();
+        fs::write(path, r#"[{"time": 0, "center": [-1, 0], "zoom": 4},
+            {"zoom": 0.04, "center": [1, 0.5], "time": 10, "palette_offset": 20}]"#).unwrap();
+        let keyframes = load_keyframes(path).unwrap();
+        assert_eq!(keyframes.len(), 2);
+        assert_eq!(keyframes[0].palette_offset, 0.0);
+
+        let middle = interpolate_keyframes(&keyframes, 5.0);
+        assert!((middle.center.x - 0.0).abs() < 1e-6 && (middle.center.y - 0.25).abs() < 1e-6);
+        assert!((middle.zoom - 0.4).abs() < 1e-5);
+        assert!((middle.palette_offset - 10.0).abs() < 1e-5);
+        assert_eq!(interpolate_keyframes(&keyframes, 20.0).zoom, 0.04);
+    }
+}
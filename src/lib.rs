@@ -0,0 +1,2745 @@
+#![cfg_attr(feature = "simd", feature(portable_simd))]
+use std::fs;
+use std::io::{self, stderr, IsTerminal, Write};
+use std::ops;
+use std::sync::{Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc::{channel, RecvTimeoutError}};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::thread;
+use std::time;
+
+mod bignum;
+mod buddhabrot;
+mod checkpoint;
+mod edges;
+mod grid;
+mod image;
+mod keyframes;
+mod metadata;
+mod perturbation;
+mod resample;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use buddhabrot::BuddhabrotConfig;
+pub use checkpoint::Checkpoint;
+pub use edges::{EdgeStyle, render_edges};
+pub use grid::draw_grid;
+pub use keyframes::{Keyframe, interpolate_keyframes, load_keyframes};
+pub use metadata::{OutputSettings, animation_metadata, render_metadata, save_metadata};
+pub use resample::{ResampleFilter, downsample};
+pub use image::{Apng, BitDepth, STDOUT_PATH, SaveOptions, ToneMap, save_bmp, save_image, save_image_auto, save_iterations, save_png, save_ppm, to_rgba8};
+
+// Precision of every coordinate and color computation, the f64 feature trades
+// some speed for deeper zooms before the image breaks up into blocks
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Complex {
+    pub r: Real,
+    pub i: Real,
+}
+
+impl Complex {
+    pub fn squared(&self) -> Complex {
+        *self * *self
+    }
+
+    pub fn add(&self, rhs: &Complex) -> Complex {
+        *self + *rhs
+    }
+
+    pub fn multiply(&self, rhs: &Complex) -> Complex {
+        *self * *rhs
+    }
+
+    pub fn div(&self, rhs: &Complex) -> Complex {
+        *self / *rhs
+    }
+
+    // Integer power by repeated multiplication, powi(2) matches squared() exactly
+    pub fn powi(&self, n: u32) -> Complex {
+        let mut result = Complex {r: 1.0, i: 0.0};
+        if n > 0 {
+            result = *self;
+            for _ in 1..n {
+                result = result * *self;
+            }
+        }
+        result
+    }
+
+    pub fn length(&self) -> Real {
+        self.length_squared().sqrt()
+    }
+
+    pub fn length_squared(&self) -> Real {
+        self.r*self.r + self.i*self.i
+    }
+
+    pub fn conjugate(&self) -> Complex {
+        Complex {r: self.r, i: -self.i}
+    }
+
+    // Complex number of magnitude r at the angle theta in radians from the positive real axis
+    pub fn from_polar(r: Real, theta: Real) -> Complex {
+        Complex {r: r * theta.cos(), i: r * theta.sin()}
+    }
+
+    // Magnitude and argument in (-pi:pi]
+    pub fn to_polar(&self) -> (Real, Real) {
+        (self.length(), self.i.atan2(self.r))
+    }
+}
+
+impl ops::Add for Complex {
+    type Output = Complex;
+
+    fn add(self, rhs: Complex) -> Complex {
+        Complex {r: self.r + rhs.r, i: self.i + rhs.i}
+    }
+}
+
+impl ops::Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex {r: self.r - rhs.r, i: self.i - rhs.i}
+    }
+}
+
+impl ops::Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex {r: self.r*rhs.r - self.i*rhs.i, i: self.r*rhs.i + self.i*rhs.r}
+    }
+}
+
+impl ops::Div for Complex {
+    type Output = Complex;
+
+    fn div(self, rhs: Complex) -> Complex {
+        let denominator = rhs.length_squared();
+        Complex {r: (self.r*rhs.r + self.i*rhs.i) / denominator, i: (self.i*rhs.r - self.r*rhs.i) / denominator}
+    }
+}
+
+impl ops::Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex {r: -self.r, i: -self.i}
+    }
+}
+
+// The recurrence iterated for every sample
+#[derive(Copy, Clone, Debug)]
+pub enum FractalKind {
+    // z = z^d + pixel, starting at z = 0
+    Mandelbrot,
+    // z = z^d + c, starting at z = pixel
+    Julia { c: Complex },
+    // z = (|Re z| + i|Im z|)^d + pixel, starting at z = 0
+    BurningShip,
+    // Tricorn or Mandelbar, z = conj(z)^d + pixel, starting at z = 0
+    Tricorn,
+    // Density of the escaping Mandelbrot orbits, see BuddhabrotConfig
+    Buddhabrot,
+    // Newton's method for z^3 - 1 starting at z = pixel, colored by the root it converges to
+    Newton,
+    // Relaxed Newton's method for z^d - 1 plus the pixel every step, z = z - relax*(z^d - 1)/(d*z^(d-1)) + pixel
+    // starting at z = 1, colored by how fast it converges
+    Nova { relax: Real },
+}
+
+impl FractalKind {
+    // One iteration of the recurrence with the exponent d
+    fn step(&self, z: &Complex, c: &Complex, exponent: u32) -> Complex {
+        match self {
+            FractalKind::Mandelbrot | FractalKind::Julia {..} | FractalKind::Buddhabrot => z.powi(exponent) + *c,
+            FractalKind::BurningShip => Complex {r: z.r.abs(), i: z.i.abs()}.powi(exponent) + *c,
+            FractalKind::Tricorn => z.conjugate().powi(exponent) + *c,
+            FractalKind::Newton => *z - newton_step(z, NEWTON_DEGREE),
+            FractalKind::Nova {relax} => *z - nova_step(z, c, *relax, exponent),
+        }
+    }
+}
+
+// Degree of the Newton fractal polynomial z^n - 1, its roots are the n-th roots of unity
+const NEWTON_DEGREE: u32 = 3;
+// Squared step length under which a Newton orbit has converged
+const NEWTON_TOLERANCE: Real = 1e-10;
+// Brightness kept per iteration a Newton orbit takes to converge
+const NEWTON_SHADE: Real = 0.9;
+
+// Newton step (z^n - 1) / (n*z^(n-1)) for the polynomial of degree n
+fn newton_step(z: &Complex, degree: u32) -> Complex {
+    let n = Complex {r: degree as Real, i: 0.0};
+    (z.powi(degree) - Complex {r: 1.0, i: 0.0}) / (n * z.powi(degree - 1))
+}
+
+// Step subtracted from z by an iteration of the Nova fractal
+fn nova_step(z: &Complex, c: &Complex, relax: Real, degree: u32) -> Complex {
+    Complex {r: relax, i: 0.0} * newton_step(z, degree) - *c
+}
+
+// How an escaped sample is mapped to the palette
+#[derive(Copy, Clone, Debug)]
+pub enum ColoringMode {
+    // One palette entry per iteration count
+    Banded,
+    // Continuous iteration count interpolated between palette entries, interior points are black
+    Smooth,
+    // Smooth coloring that fades to black away from the boundary, using the exterior distance estimate
+    Distance,
+    // Palette position from the share of escaped pixels that escape no later, spreads
+    // the palette evenly over the image however the iteration counts are distributed
+    Histogram,
+    // Palette position from the closest the orbit comes to the trap, interior points included
+    OrbitTrap {trap: OrbitTrap},
+    // Smooth coloring lit as an embossed surface, the distance estimate gradient is the normal, see Light
+    Slope,
+    // Gray ramp from white for immediate escape to black at the iteration limit and inside the set
+    Grayscale,
+}
+
+// Shape the orbit distance is measured to for orbit trap coloring
+#[derive(Copy, Clone, Debug)]
+pub enum OrbitTrap {
+    Point {center: Complex},
+    // The line Im z = im
+    Horizontal {im: Real},
+    // The line Re z = re
+    Vertical {re: Real},
+}
+
+impl OrbitTrap {
+    fn distance(&self, z: &Complex) -> Real {
+        match self {
+            OrbitTrap::Point {center} => (*z - *center).length(),
+            OrbitTrap::Horizontal {im} => (z.i - im).abs(),
+            OrbitTrap::Vertical {re} => (z.r - re).abs(),
+        }
+    }
+}
+
+// Direction of the light of slope coloring
+#[derive(Copy, Clone, Debug)]
+pub struct Light {
+    // Direction in the image plane in degrees, counterclockwise from the positive real axis
+    pub angle: Real,
+    // Height of the light above the plane, higher lights flatten the relief
+    pub height: Real,
+}
+
+impl Light {
+    pub fn new() -> Light {
+        Light {angle: 45.0, height: 1.5}
+    }
+
+    // Lambertian intensity in [0:1] of a surface with the normal direction u, |u| = 1
+    fn intensity(&self, u: &Complex) -> Real {
+        let angle = self.angle.to_radians();
+        let t = u.r * angle.cos() + u.i * angle.sin() + self.height;
+        (t / (1.0 + self.height)).max(0.0)
+    }
+}
+
+impl Default for Light {
+    fn default() -> Light {
+        Light::new()
+    }
+}
+
+// Transform of the iteration count before the banded, smooth and distance palette lookups
+#[derive(Copy, Clone, Debug)]
+pub enum ColorScale {
+    Linear,
+    // Bands widen as the count grows, 4 palette entries per unit of sqrt(n)
+    Sqrt,
+    // Bands widen with the count like the escape time near the boundary, 16 palette entries per unit of ln(1 + n)
+    Log,
+}
+
+impl ColorScale {
+    fn apply(&self, iterations: Real) -> Real {
+        match self {
+            ColorScale::Linear => iterations,
+            ColorScale::Sqrt => iterations.max(0.0).sqrt() * 4.0,
+            ColorScale::Log => iterations.max(0.0).ln_1p() * 16.0,
+        }
+    }
+}
+
+// How samples inside the set are colored, from the last z of their orbit
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InteriorColoring {
+    // Every interior sample gets the same color
+    Flat,
+    // Palette position from |z|, relative to the bailout radius
+    Magnitude,
+    // Palette position from the angle of z, once around the palette per turn
+    Angle,
+    // Glow in the brightest palette color fading from the boundary into the interior, by the
+    // distance estimate of the attracting cycle of the orbit. Mandelbrot set with exponent 2 only
+    Distance,
+}
+
+// How the samples of a pixel are placed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplingMode {
+    // Uniformly random over the whole pixel
+    Random,
+    // One random sample per cell of a k*k grid over the pixel, k*k close to the sample count
+    Stratified,
+    // A single sample at the pixel center without drawing random numbers, deterministic without a seed
+    Center,
+}
+
+// Order the workers pull the tiles from the queue in, the image is the same in every order
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TileOrder {
+    // Row by row from the top left tile
+    Row,
+    // Rings of tiles around the center tile, each ring clockwise from the right
+    Spiral,
+    // By the distance of the tile center to the center of the image
+    CenterOut,
+}
+
+// Encoding of the palette, inside and edge colors
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColorSpace {
+    // Display values like color pickers give, decoded to linear light before they are blended
+    Srgb,
+    // Already linear light intensities, blended as they are
+    Linear,
+}
+
+// Rectangle of the image in pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Vec2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl Vec2<usize> {
+    pub fn new() -> Vec2<usize> {
+        Vec2{x:0, y:0}
+    }
+}
+
+impl Vec2<Real> {
+    pub fn new() -> Vec2<Real> {
+        Vec2{x:0.0, y:0.0}
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct Color {
+    pub r: Real,
+    pub g: Real,
+    pub b: Real,
+    pub a: Real,
+}
+
+impl Color {
+    pub fn new() -> Color {
+        Color {r:0.0, g:0.0, b:0.0, a:0.0}
+    }
+
+    // Opaque color from its channels
+    pub const fn rgb(r: Real, g: Real, b: Real) -> Color {
+        Color {r, g, b, a: 1.0}
+    }
+
+    pub const fn rgba(r: Real, g: Real, b: Real, a: Real) -> Color {
+        Color {r, g, b, a}
+    }
+
+    #[deprecated(note = "use color = color + rhs")]
+    pub fn add(&mut self, rhs: Color) {
+        self.r += rhs.r;
+        self.g += rhs.g;
+        self.b += rhs.b;
+        self.a += rhs.a;
+    }
+
+    #[deprecated(note = "use color = color / value")]
+    pub fn divide(&mut self, value: Real) {
+        self.r /= value;
+        self.g /= value;
+        self.b /= value;
+        self.a /= value;
+    }
+
+    // Linear interpolation, t = 0 gives a and t = 1 gives b
+    pub fn lerp(a: Color, b: Color, t: Real) -> Color {
+        a + (b - a) * t
+    }
+
+    // Opaque color of hue in degrees, saturation and value in [0:1]
+    pub fn from_hsv(hue: Real, saturation: Real, value: Real) -> Color {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Color::rgb(r + m, g + m, b + m)
+    }
+
+    // Decodes sRGB encoded channels to linear light, alpha is left as is
+    pub fn to_linear(&self) -> Color {
+        Color {r: srgb_to_linear(self.r), g: srgb_to_linear(self.g), b: srgb_to_linear(self.b), a: self.a}
+    }
+
+    // Encodes linear channels with the sRGB transfer function, alpha is left as is
+    pub fn to_srgb(&self) -> Color {
+        Color {r: linear_to_srgb(self.r), g: linear_to_srgb(self.g), b: linear_to_srgb(self.b), a: self.a}
+    }
+}
+
+// Channel wise arithmetic, alpha included, for accumulating and averaging samples
+impl ops::Add for Color {
+    type Output = Color;
+
+    fn add(self, rhs: Color) -> Color {
+        Color::rgba(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b, self.a + rhs.a)
+    }
+}
+
+impl ops::Sub for Color {
+    type Output = Color;
+
+    fn sub(self, rhs: Color) -> Color {
+        Color::rgba(self.r - rhs.r, self.g - rhs.g, self.b - rhs.b, self.a - rhs.a)
+    }
+}
+
+impl ops::Mul<Real> for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: Real) -> Color {
+        Color::rgba(self.r * rhs, self.g * rhs, self.b * rhs, self.a * rhs)
+    }
+}
+
+impl ops::Div<Real> for Color {
+    type Output = Color;
+
+    fn div(self, rhs: Real) -> Color {
+        Color::rgba(self.r / rhs, self.g / rhs, self.b / rhs, self.a / rhs)
+    }
+}
+
+// Color stops the palette colorings interpolate between, derefs to the stops
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<Color>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Color>) -> Palette {
+        Palette {colors}
+    }
+
+    // Color at t in [0:1] spread across the stops, from the first at 0 to the last at 1,
+    // t outside is clamped
+    pub fn sample(&self, t: Real) -> Color {
+        let last = self.colors.len() - 1;
+        let position = clamp(t, 0.0, 1.0) * (last as Real);
+        let index = position.floor() as usize;
+        Color::lerp(self.colors[index], self.colors[min(index + 1, last)], position.fract())
+    }
+
+    // Color at a position counted in stops for cycling through the palette, wrapping around
+    // past the last stop which blends back into the first
+    pub fn cycle(&self, position: Real) -> Color {
+        let length = self.colors.len();
+        let position = position.rem_euclid(length as Real);
+        let index = position.floor() as usize;
+        Color::lerp(self.colors[index%length], self.colors[(index+1)%length], position.fract())
+    }
+
+    // Stop of the highest Rec. 709 luminance
+    pub fn brightest(&self) -> Color {
+        let luminance = |color: &Color| 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+        self.colors.iter().copied().fold(self.colors[0], |brightest, color| if luminance(&color) > luminance(&brightest) { color } else { brightest })
+    }
+
+    // The same stops converted from sRGB to linear light
+    pub fn to_linear(&self) -> Palette {
+        Palette::new(self.colors.iter().map(Color::to_linear).collect())
+    }
+}
+
+impl ops::Deref for Palette {
+    type Target = [Color];
+
+    fn deref(&self) -> &[Color] {
+        &self.colors
+    }
+}
+
+fn srgb_to_linear(value: Real) -> Real {
+    if value <= 0.04045 {
+        return value / 12.92;
+    }
+    ((value + 0.055) / 1.055).powf(2.4)
+}
+
+fn linear_to_srgb(value: Real) -> Real {
+    if value <= 0.0031308 {
+        return value * 12.92;
+    }
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+}
+
+// Prefixes an I/O error with the path it happened on
+fn with_path(path: &str, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {}", path, error))
+}
+
+// Loads a palette of RGB triplets in [0:1], either as a text file with one
+// "r g b" line per color or as a JSON array of [r, g, b] arrays
+pub fn load_palette(path: &str) -> io::Result<Palette> {
+    let invalid = |message: String| with_path(path, io::Error::new(io::ErrorKind::InvalidData, message));
+    let text = fs::read_to_string(path).map_err(|e| with_path(path, e))?;
+
+    let mut channels = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || c == ',' || c == '[' || c == ']') {
+        if token.is_empty() {
+            continue;
+        }
+        match token.parse::<Real>() {
+            Ok(value) => channels.push(value),
+            Err(_) => return Err(invalid(format!("invalid channel value '{}'", token))),
+        }
+    }
+    if channels.is_empty() || channels.len() % 3 != 0 {
+        return Err(invalid(format!("expected RGB triplets, found {} values", channels.len())));
+    }
+
+    Ok(Palette::new(channels.chunks(3).map(|rgb| Color::rgb(rgb[0], rgb[1], rgb[2])).collect()))
+}
+
+// Palette of steps colors sweeping the hue wheel once from hue, at a fixed saturation and value
+pub fn hsv_palette(hue: Real, saturation: Real, value: Real, steps: usize) -> Palette {
+    Palette::new((0..steps).map(|step| Color::from_hsv(hue + 360.0 * (step as Real) / (steps as Real), saturation, value)).collect())
+}
+
+// Prints the progress [0:100] as a bar on stderr, followed by the
+// estimated remaining time and the throughput once some tiles are finished
+fn print_progress(progress: u32, finished_tiles: usize, elapsed: time::Duration) {
+    let mut progress_bar = String::from("[");
+    for i in 0..50 {
+        if i < progress/2 {
+            progress_bar.push('=');
+        }
+        else {
+            progress_bar.push(' ');
+        }
+    }
+    progress_bar.push(']');
+    let elapsed = elapsed.as_secs_f64();
+    if progress == 0 || finished_tiles == 0 || elapsed <= 0.0 {
+        eprint!("\r{} {}%  ETA --  ", progress_bar, progress);
+    }
+    else {
+        let remaining = (elapsed * (100 - progress) as f64 / progress as f64) as u64;
+        let tiles_per_second = finished_tiles as f64 / elapsed;
+        eprint!("\r{} {}%  ETA {}h{}m{}s  {:.1} tiles/s  ", progress_bar, progress, remaining/60/60, (remaining/60)%60, remaining%60, tiles_per_second);
+    }
+    stderr().flush().unwrap();
+}
+
+// Hours, minutes and seconds of a duration as h:mm:ss
+fn format_duration(seconds: u64) -> String {
+    format!("{}:{:02}:{:02}", seconds/60/60, (seconds/60)%60, seconds%60)
+}
+
+// Prints the progress [0:100] as a colored bar of block characters drawn to the eighth of a
+// cell, after a spinner frame and followed by the elapsed and estimated remaining time
+fn print_fancy_progress(progress: u32, finished_tiles: usize, elapsed: time::Duration, frame: usize) {
+    let spinner = PROGRESS_SPINNER[frame % PROGRESS_SPINNER.len()];
+    let eighths = progress as usize * PROGRESS_BAR_WIDTH * 8 / 100;
+    let mut progress_bar: String = "\u{2588}".repeat(eighths / 8);
+    let partial = eighths % 8;
+    if partial > 0 {
+        progress_bar.push(PROGRESS_EIGHTHS[partial - 1]);
+    }
+    let filled = progress_bar.chars().count();
+    progress_bar.push_str(&" ".repeat(PROGRESS_BAR_WIDTH - filled));
+    let seconds = elapsed.as_secs_f64();
+    let remaining = match progress == 0 || finished_tiles == 0 || seconds <= 0.0 {
+        true => String::from("--"),
+        false => format_duration((seconds * (100 - progress) as f64 / progress as f64) as u64),
+    };
+    // Cyan bar on a dark gray track, the rest of the line is cleared
+    eprint!("\r\x1b[36m{}\x1b[0m \x1b[36;100m{}\x1b[0m {:>3}%  {} elapsed  ETA {}\x1b[K",
+        spinner, progress_bar, progress, format_duration(elapsed.as_secs()), remaining);
+    stderr().flush().unwrap();
+}
+
+// How ProgressReport draws the progress
+#[derive(Copy, Clone, PartialEq)]
+enum ProgressStyle {
+    // A line every PROGRESS_LOG_STEP percent, so logs don't collect every redraw
+    Lines,
+    // The ASCII bar of print_progress, for terminals without colors
+    Bar,
+    // The bar of print_fancy_progress
+    Fancy,
+}
+
+impl ProgressStyle {
+    // Fancy on a terminal unless it is dumb or NO_COLOR is set
+    fn of_stderr() -> ProgressStyle {
+        if !stderr().is_terminal() {
+            return ProgressStyle::Lines;
+        }
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        match dumb || std::env::var_os("NO_COLOR").is_some() {
+            true => ProgressStyle::Bar,
+            false => ProgressStyle::Fancy,
+        }
+    }
+}
+
+// Progress of a render on stderr, redrawn in place on a terminal
+struct ProgressReport {
+    start_time: time::Instant,
+    style: ProgressStyle,
+    // Last step printed as a line
+    logged: Option<u32>,
+    // Spinner frame of the next redraw
+    frame: usize,
+}
+
+impl ProgressReport {
+    fn start() -> ProgressReport {
+        let mut report = ProgressReport {start_time: time::Instant::now(), style: ProgressStyle::of_stderr(), logged: None, frame: 0};
+        report.print(0, 0);
+        report
+    }
+
+    fn print(&mut self, progress: u32, finished_tiles: usize) {
+        let elapsed = self.start_time.elapsed();
+        match self.style {
+            ProgressStyle::Fancy => {
+                print_fancy_progress(progress, finished_tiles, elapsed, self.frame);
+                self.frame += 1;
+                return;
+            }
+            ProgressStyle::Bar => {
+                print_progress(progress, finished_tiles, elapsed);
+                return;
+            }
+            ProgressStyle::Lines => {}
+        }
+        let step = progress / PROGRESS_LOG_STEP;
+        if self.logged.is_some_and(|logged| logged >= step) {
+            return;
+        }
+        self.logged = Some(step);
+        eprintln!("{}% after {:.1}s", progress, elapsed.as_secs_f64());
+    }
+}
+
+// Prints a line of a finished tile, above the progress bar on a terminal which is redrawn after it
+fn print_tile(id: usize, desc: &ThreadDescryptor, work: &Work, elapsed: time::Duration) {
+    let tile = format!("tile {} at {},{}: {} pixels, {} iterations in {:.1}ms",
+        id, desc.offset.x, desc.offset.y, work.pixels, work.iterations, elapsed.as_secs_f64() * 1000.0);
+    match stderr().is_terminal() {
+        true => eprintln!("\r{:<80}", tile),
+        false => eprintln!("{}", tile),
+    }
+}
+
+fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
+    if value < min {
+        return min;
+    }
+    else if value > max {
+        return max;
+    }
+    value
+}
+
+// False for zero, negative, infinite and NaN values
+fn is_positive(value: Real) -> bool {
+    value > 0.0 && value.is_finite()
+}
+
+// is_multiple_of would need Rust 1.87
+#[allow(clippy::manual_is_multiple_of)]
+fn divide_roundup(numinator: usize, denominator: usize) -> usize {
+    if numinator % denominator == 0 {
+        return numinator/denominator;
+    }
+    numinator/denominator+1
+}
+
+fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if a < b {
+        return a;
+    }
+    b
+}
+
+// Render Parameters
+// COLOR PALLETE SOURCE: https://stackoverflow.com/a/16505538/9218594
+const COLOR_PALETTE: [Color; 16] = [
+    Color {r:0.26, g:0.1,  b:0.06, a:1.0},
+    Color {r:0.1,  g:0.03, b:0.1,  a:1.0},
+    Color {r:0.3,  g:0.01, b:0.18, a:1.0},
+    Color {r:0.02, g:0.02, b:0.28, a:1.0},
+    Color {r:0.0,  g:0.03, b:0.4,  a:1.0},
+    Color {r:0.05, g:0.17, b:0.54, a:1.0},
+    Color {r:0.1,  g:0.3,  b:0.7,  a:1.0},
+    Color {r:0.25, g:0.5,  b:0.82, a:1.0},
+    Color {r:0.53, g:0.71, b:0.9,  a:1.0},
+    Color {r:0.83, g:0.93, b:0.97, a:1.0},
+    Color {r:0.95, g:0.91, b:0.75, a:1.0},
+    Color {r:0.97, g:0.78, b:0.37, a:1.0},
+    Color {r:1.0,  g:0.67, b:0.0,  a:1.0},
+    Color {r:0.8,  g:0.5,  b:0.0,  a:1.0},
+    Color {r:0.6,  g:0.34, b:0.0,  a:1.0},
+    Color {r:0.42, g:0.2,  b:0.02, a:1.0}
+    ];
+// Default of RenderConfig::inside_color
+const INTERIOR_COLOR: Color = Color::rgb(0.0, 0.0, 0.0);
+// Distance from the boundary, in pixels, at which the distance estimation glow fades out
+const DISTANCE_GLOW_WIDTH: Real = 4.0;
+// Distance from the boundary, in pixels, over which the interior distance glow fades by a factor of e
+const INTERIOR_GLOW_WIDTH: Real = 12.0;
+// Longest attracting cycle the interior distance estimation looks for
+const MAX_INTERIOR_PERIOD: u32 = 256;
+// Squared distance within which the orbit is back at its start after a period
+const INTERIOR_PERIOD_TOLERANCE: Real = 1e-10;
+// Newton steps refining the point of the cycle
+const INTERIOR_NEWTON_STEPS: u32 = 4;
+// Defaults of RenderConfig::max_iterations and RenderConfig::bailout
+const MAX_ITERATIONS: u32 = 250;
+// Palette entries per unit of orbit trap distance
+const TRAP_PALETTE_SCALE: Real = 16.0;
+// Iterations before the first periodicity check reference point is replaced
+const PERIODICITY_CHECK_INTERVAL: u32 = 8;
+const MAX_LENGTH: Real = 2.0;
+// Time between two redraws of the progress bar while no tile finishes
+const PROGRESS_INTERVAL: time::Duration = time::Duration::from_millis(100);
+// Percent between the progress lines printed when stderr isn't a terminal
+const PROGRESS_LOG_STEP: u32 = 10;
+// Cells of the bar of print_fancy_progress, the partly filled cell at its end and the spinner before it
+const PROGRESS_BAR_WIDTH: usize = 30;
+const PROGRESS_EIGHTHS: [char; 7] = ['\u{258F}', '\u{258E}', '\u{258D}', '\u{258C}', '\u{258B}', '\u{258A}', '\u{2589}'];
+const PROGRESS_SPINNER: [char; 10] = ['\u{280B}', '\u{2819}', '\u{2839}', '\u{2838}', '\u{283C}', '\u{2834}', '\u{2826}', '\u{2827}', '\u{2807}', '\u{280F}'];
+// Time between two checkpoints of a resumable render
+const CHECKPOINT_INTERVAL: time::Duration = time::Duration::from_secs(60);
+// Default of RenderConfig::tile_size
+const TILE_SIZE: usize = 64;
+// Samples per pixel adaptive sampling starts with
+const ADAPTIVE_SAMPLES: usize = 4;
+// Rectangles of fill_rectangles up to this side are rendered instead of split further
+const FILL_MIN_SIZE: usize = 6;
+
+// Parameters of a single render, the returned color buffer is row major
+#[derive(Clone, Debug)]
+pub struct RenderConfig {
+    pub width: usize,
+    pub height: usize,
+    pub center: Vec2<Real>,
+    pub view_size: Vec2<Real>,
+    // The imaginary axis points up the image like in a plot, flip_y turns it down the
+    // image like the rows of the buffer, mirroring the image
+    pub flip_y: bool,
+    pub sample_count: usize,
+    pub sampling: SamplingMode,
+    // Number of worker threads, 0 uses the available parallelism
+    pub thread_count: usize,
+    // Width and height of the square tiles workers pull from the queue, smaller
+    // tiles balance the load better at the cost of some scheduling overhead
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    pub fractal: FractalKind,
+    pub exponent: u32,
+    // Iterations after which a sample is considered inside the set, deeper zooms need more
+    pub max_iterations: u32,
+    // Escape radius, a larger one gives smoother continuous coloring
+    pub bailout: Real,
+    pub coloring: ColoringMode,
+    pub color_scale: ColorScale,
+    pub interior: InteriorColoring,
+    pub light: Light,
+    pub palette: Palette,
+    // Color of the samples that never escape, unless InteriorColoring or the coloring mode colors them
+    pub inside_color: Color,
+    // Palette entries every lookup of the cyclic colorings is shifted by, animating it cycles the colors
+    pub palette_offset: Real,
+    // Palette entries per iteration of the banded and smooth colorings, below 1 gives fewer and wider bands
+    pub color_density: Real,
+    // Treats the palette as sRGB and blends it in linear light, the returned buffer is then
+    // linear and has to be encoded again when saved, see SaveOptions::gamma
+    pub gamma: bool,
+    // Encoding of the palette and inside color, only decoded with gamma
+    pub color_space: ColorSpace,
+    // Seed of the sampling jitter, None picks a random one every render
+    pub seed: Option<u64>,
+    pub buddhabrot: BuddhabrotConfig,
+    // Renders only this rectangle of the image, the returned buffer has its size while
+    // the view still spans the whole image so regions of several renders line up
+    pub region: Option<Region>,
+    // Iterates the Mandelbrot set by perturbation around an f64 reference orbit of every tile,
+    // needed for zooms where neighboring pixels can't be told apart in Real
+    pub deep_zoom: bool,
+    // Iterates every sample in fixed point numbers with this many fractional bits instead of
+    // Real, slow but exact at any zoom
+    pub precision: Option<u32>,
+    // Decimal digits of the center, see set_center_digits
+    pub center_digits: Option<(String, String)>,
+    // Takes the full sample count only where the color variance of the first ADAPTIVE_SAMPLES
+    // samples of a pixel is above this, flat areas get away with fewer samples
+    pub adaptive_threshold: Option<Real>,
+    // Takes between 1 and the full sample count per pixel, as many as the gradient of the escape
+    // level of the pixel centers to their neighbors calls for, gradients of this or more take every sample
+    pub detail_threshold: Option<Real>,
+    // Fills rectangles whose border pixels all escape at the same iteration without rendering their
+    // inside, see fill_rectangles. Much faster over large flat areas but misses details smaller than them
+    pub fast_fill: bool,
+    // Fills every tile with a solid color from its index instead of the fractal, neighboring tiles
+    // differ in brightness so seams and pixels no tile covers stand out
+    pub debug_tiles: bool,
+    // Prints a progress bar to stderr while rendering
+    pub progress: bool,
+    // Prints the render time and pixel count of every tile to stderr as it finishes
+    pub verbose: bool,
+}
+
+impl RenderConfig {
+    pub fn new() -> RenderConfig {
+        let mut config = RenderConfig {
+            width: 1366, // 7680; // 3840; // 1366;
+            height: 768, // 4320; // 2160; // 768;
+            center: Vec2::<Real>{x: -0.7453, y: 0.1127},
+            view_size: Vec2::<Real>::new(),
+            flip_y: false,
+            sample_count: 16,
+            sampling: SamplingMode::Stratified,
+            thread_count: 0,
+            tile_size: TILE_SIZE,
+            tile_order: TileOrder::Row,
+            fractal: FractalKind::Mandelbrot,
+            exponent: 2,
+            max_iterations: MAX_ITERATIONS,
+            bailout: MAX_LENGTH,
+            coloring: ColoringMode::Banded,
+            color_scale: ColorScale::Linear,
+            interior: InteriorColoring::Flat,
+            light: Light::new(),
+            palette: Palette::new(COLOR_PALETTE.to_vec()),
+            inside_color: INTERIOR_COLOR,
+            palette_offset: 0.0,
+            color_density: 1.0,
+            gamma: true,
+            color_space: ColorSpace::Srgb,
+            seed: None,
+            buddhabrot: BuddhabrotConfig::new(),
+            region: None,
+            deep_zoom: false,
+            precision: None,
+            center_digits: None,
+            adaptive_threshold: None,
+            detail_threshold: None,
+            fast_fill: false,
+            debug_tiles: false,
+            progress: true,
+            verbose: false,
+        };
+        config.set_zoom(6.5E-4);
+        config
+    }
+
+    // Checks every parameter the renderer divides by or iterates over, the
+    // error names the first invalid one
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("image size must be at least 1x1, got {}x{}", self.width, self.height));
+        }
+        if !is_positive(self.view_size.x) || !is_positive(self.view_size.y) {
+            return Err(format!("view size must be positive, got {}x{}", self.view_size.x, self.view_size.y));
+        }
+        if !self.center.x.is_finite() || !self.center.y.is_finite() {
+            return Err(format!("center must be finite, got {},{}", self.center.x, self.center.y));
+        }
+        if self.sample_count == 0 {
+            return Err(String::from("sample count must be at least 1"));
+        }
+        if self.sampling == SamplingMode::Center && self.sample_count != 1 {
+            return Err(format!("center sampling takes a single sample per pixel, got {}", self.sample_count));
+        }
+        if self.tile_size == 0 {
+            return Err(String::from("tile size must be at least 1"));
+        }
+        if self.max_iterations == 0 {
+            return Err(String::from("max iterations must be at least 1"));
+        }
+        if !is_positive(self.bailout) {
+            return Err(format!("bailout radius must be positive, got {}", self.bailout));
+        }
+        if self.palette.is_empty() {
+            return Err(String::from("palette must have at least one color"));
+        }
+        if !is_positive(self.color_density) {
+            return Err(format!("color density must be positive, got {}", self.color_density));
+        }
+        if !self.palette_offset.is_finite() {
+            return Err(format!("palette offset must be finite, got {}", self.palette_offset));
+        }
+        if self.deep_zoom && !(matches!(self.fractal, FractalKind::Mandelbrot) && self.exponent == 2) {
+            return Err(String::from("deep zoom only supports the Mandelbrot set with exponent 2"));
+        }
+        if self.adaptive_threshold.is_some_and(|threshold| !is_positive(threshold)) {
+            return Err(format!("adaptive sampling threshold must be positive, got {}", self.adaptive_threshold.unwrap()));
+        }
+        if let Some(threshold) = self.detail_threshold {
+            if !is_positive(threshold) {
+                return Err(format!("detail sampling threshold must be positive, got {}", threshold));
+            }
+            if self.adaptive_threshold.is_some() {
+                return Err(String::from("adaptive and detail sampling can't be combined"));
+            }
+        }
+        if self.interior == InteriorColoring::Distance && !(matches!(self.fractal, FractalKind::Mandelbrot) && self.exponent == 2 && !self.deep_zoom && self.precision.is_none()) {
+            return Err(String::from("interior distance only supports the Mandelbrot set with exponent 2, without deep zoom or arbitrary precision"));
+        }
+        // A filled rectangle takes one color, so the color of a sample may only follow its iteration count
+        let banded = matches!(self.coloring, ColoringMode::Banded | ColoringMode::Histogram) && self.interior == InteriorColoring::Flat;
+        if self.fast_fill && (!banded || matches!(self.fractal, FractalKind::Newton)) {
+            return Err(String::from("fast fill only supports the banded and histogram colorings with a flat interior color, outside the newton mode"));
+        }
+        if let FractalKind::Nova {relax} = self.fractal {
+            if !relax.is_finite() {
+                return Err(format!("nova relaxation must be finite, got {}", relax));
+            }
+            if self.exponent < 2 {
+                return Err(format!("nova power must be at least 2, got {}", self.exponent));
+            }
+        }
+        if let Some(bits) = self.precision {
+            if self.deep_zoom {
+                return Err(String::from("deep zoom and arbitrary precision can't be combined"));
+            }
+            bignum::validate(bits, &self.fractal, self.exponent, self.bailout)?;
+        }
+        if let Some(region) = self.region {
+            if region.width == 0 || region.height == 0 || region.x + region.width > self.width || region.y + region.height > self.height {
+                return Err(format!("region {}x{} at {},{} must be non-empty and inside the {}x{} image",
+                    region.width, region.height, region.x, region.y, self.width, self.height));
+            }
+        }
+        Ok(())
+    }
+
+    // Rectangle of the image that is rendered, the whole image unless region is set
+    pub fn output_region(&self) -> Region {
+        self.region.unwrap_or(Region {x: 0, y: 0, width: self.width, height: self.height})
+    }
+
+    // Moves the view to the preset location of the Mandelbrot set
+    pub fn apply_preset(&mut self, preset: &Preset) {
+        self.fractal = FractalKind::Mandelbrot;
+        self.center = preset.center;
+        self.max_iterations = preset.max_iterations;
+        self.set_zoom(preset.zoom);
+    }
+
+    // Centers the view on decimal coordinates like "-1.7693831791955150182138472860", config.center is
+    // rounded from them and --precision iterates from every digit, as long as the center isn't moved
+    pub fn set_center_digits(&mut self, x: &str, y: &str) -> Result<(), String> {
+        let parse = |digits: &str| -> Result<Real, String> {
+            bignum::check_decimal(digits)?;
+            digits.parse::<Real>().map_err(|_| format!("{} is not a decimal number", digits))
+        };
+        self.center = Vec2::<Real>{x: parse(x)?, y: parse(y)?};
+        self.center_digits = Some((x.to_string(), y.to_string()));
+        Ok(())
+    }
+
+    // Sets the view height to zoom, the view width follows the image aspect ratio
+    pub fn set_zoom(&mut self, zoom: Real) {
+        let aspect_ratio = (self.width as Real) / (self.height as Real);
+        self.view_size = Vec2::<Real>{x: zoom * aspect_ratio, y: zoom};
+    }
+
+    // Sets the view to the rectangle [x_min:x_max] x [y_min:y_max] of the complex plane
+    pub fn set_bounds(&mut self, x_min: Real, x_max: Real, y_min: Real, y_max: Real) -> Result<(), String> {
+        if x_max.partial_cmp(&x_min) != Some(std::cmp::Ordering::Greater) {
+            return Err(format!("x_max ({}) must be greater than x_min ({})", x_max, x_min));
+        }
+        if y_max.partial_cmp(&y_min) != Some(std::cmp::Ordering::Greater) {
+            return Err(format!("y_max ({}) must be greater than y_min ({})", y_max, y_min));
+        }
+        self.center = Vec2::<Real>{x: (x_min + x_max) / 2.0, y: (y_min + y_max) / 2.0};
+        self.view_size = Vec2::<Real>{x: x_max - x_min, y: y_max - y_min};
+        Ok(())
+    }
+
+    // Width of a pixel in the complex plane over its height, 1 unless the view
+    // aspect differs from the image aspect and the fractal is stretched
+    pub fn pixel_aspect(&self) -> Real {
+        (self.view_size.x / self.width as Real) / (self.view_size.y / self.height as Real)
+    }
+
+    // Whether the configured colors are decoded from sRGB before they are blended in linear light
+    pub(crate) fn decodes_srgb(&self) -> bool {
+        self.gamma && self.color_space == ColorSpace::Srgb
+    }
+
+    // Sets max_iterations to base plus per_decade for every power of ten the view is narrower
+    // than 1, deeper zooms need more iterations to resolve the boundary
+    pub fn set_auto_iterations(&mut self, base: Real, per_decade: Real) {
+        let decades = (-self.view_size.x.log10()).max(0.0);
+        self.max_iterations = (base + per_decade * decades).round().max(1.0) as u32;
+    }
+
+    // Widens or heightens the view around its center until its aspect matches the image,
+    // everything inside the previous view stays visible
+    pub fn preserve_aspect(&mut self) {
+        let aspect_ratio = (self.width as Real) / (self.height as Real);
+        if self.pixel_aspect() > 1.0 {
+            self.view_size.y = self.view_size.x / aspect_ratio;
+        }
+        else {
+            self.view_size.x = self.view_size.y * aspect_ratio;
+        }
+    }
+}
+
+// Named view of a well known location of the Mandelbrot set
+pub struct Preset {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub center: Vec2<Real>,
+    pub zoom: Real,
+    // Iterations needed for the detail at this zoom
+    pub max_iterations: u32,
+}
+
+pub const PRESETS: [Preset; 4] = [
+    Preset {name: "seahorse", title: "Seahorse Valley", center: Vec2::<Real>{x: -0.7463, y: 0.1102}, zoom: 0.006, max_iterations: 500},
+    Preset {name: "elephant", title: "Elephant Valley", center: Vec2::<Real>{x: 0.2925, y: 0.0149}, zoom: 0.006, max_iterations: 500},
+    Preset {name: "triple-spiral", title: "Triple Spiral", center: Vec2::<Real>{x: -0.090, y: 0.6543}, zoom: 0.0015, max_iterations: 1000},
+    Preset {name: "mini", title: "Mini Mandelbrot", center: Vec2::<Real>{x: -1.754_877_7, y: 0.0}, zoom: 0.04, max_iterations: 1000},
+];
+
+pub fn find_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+// Geometric interpolation between two view sizes, t in [0:1], so that a zoom
+// sequence rendered at evenly spaced t appears to zoom at a constant speed
+pub fn interpolate_zoom(start: Real, end: Real, t: Real) -> Real {
+    start * (end / start).powf(t)
+}
+
+impl Default for RenderConfig {
+    fn default() -> RenderConfig {
+        RenderConfig::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ThreadDescryptor {
+    offset: Vec2<usize>,
+    thread_size: Vec2<usize>,
+    color_buffer_size: Vec2<usize>,
+    sample_count: usize,
+    sampling: SamplingMode,
+    center: Vec2<Real>,
+    view_size: Vec2<Real>,
+    fractal: FractalKind,
+    exponent: u32,
+    max_iterations: u32,
+    bailout: Real,
+    coloring: ColoringMode,
+    color_scale: ColorScale,
+    interior: InteriorColoring,
+    palette_offset: Real,
+    color_density: Real,
+    light: Light,
+    inside_color: Color,
+    deep_zoom: bool,
+    precision: Option<u32>,
+    adaptive_threshold: Option<Real>,
+    fast_fill: bool,
+    tile_size: usize,
+    debug_tiles: bool,
+}
+
+impl ThreadDescryptor {
+    fn new() -> ThreadDescryptor {
+        ThreadDescryptor {
+            offset: Vec2::<usize>::new(),
+            thread_size: Vec2::<usize>::new(),
+            color_buffer_size: Vec2::<usize>::new(),
+            sample_count: 0,
+            sampling: SamplingMode::Random,
+            center: Vec2::<Real>::new(),
+            view_size: Vec2::<Real>::new(),
+            fractal: FractalKind::Mandelbrot,
+            exponent: 2,
+            max_iterations: MAX_ITERATIONS,
+            bailout: MAX_LENGTH,
+            coloring: ColoringMode::Banded,
+            color_scale: ColorScale::Linear,
+            interior: InteriorColoring::Flat,
+            palette_offset: 0.0,
+            color_density: 1.0,
+            light: Light::new(),
+            inside_color: INTERIOR_COLOR,
+            deep_zoom: false,
+            precision: None,
+            adaptive_threshold: None,
+            fast_fill: false,
+            tile_size: TILE_SIZE,
+            debug_tiles: false,
+        }
+    }
+
+    // Descryptor of the whole image, offset and size are left to the caller
+    fn from_config(config: &RenderConfig) -> ThreadDescryptor {
+        let mut desc = ThreadDescryptor::new();
+        desc.color_buffer_size = Vec2::<usize>{x: config.width, y: config.height};
+        desc.sample_count = config.sample_count;
+        desc.sampling = config.sampling;
+        desc.center = config.center;
+        desc.view_size = config.view_size;
+        // Every pixel mapping measures the imaginary part up from the center by the view height
+        if config.flip_y {
+            desc.view_size.y = -desc.view_size.y;
+        }
+        desc.fractal = config.fractal;
+        desc.exponent = config.exponent;
+        desc.max_iterations = config.max_iterations;
+        desc.bailout = config.bailout;
+        desc.coloring = config.coloring;
+        desc.color_scale = config.color_scale;
+        desc.interior = config.interior;
+        desc.palette_offset = config.palette_offset;
+        desc.color_density = config.color_density;
+        desc.light = config.light;
+        // Blended in linear light like the palette
+        desc.inside_color = if config.decodes_srgb() { config.inside_color.to_linear() } else { config.inside_color };
+        desc.deep_zoom = config.deep_zoom;
+        desc.precision = config.precision;
+        desc.adaptive_threshold = config.adaptive_threshold;
+        desc.fast_fill = config.fast_fill;
+        desc.tile_size = config.tile_size;
+        desc.debug_tiles = config.debug_tiles;
+        desc
+    }
+
+    // Column and row of the tile in the grid over the whole image
+    fn tile_position(&self) -> Vec2<usize> {
+        Vec2::<usize>{x: self.offset.x / self.tile_size, y: self.offset.y / self.tile_size}
+    }
+
+    // Index of the tile in the row major grid over the whole image
+    fn tile_index(&self) -> usize {
+        let position = self.tile_position();
+        position.y * divide_roundup(self.color_buffer_size.x, self.tile_size) + position.x
+    }
+}
+
+// True if c lies in the main cardioid or the period-2 bulb of the Mandelbrot set,
+// both are known to be in the set so the iteration can be skipped
+fn in_main_cardioid_or_bulb(c: &Complex) -> bool {
+    let x = c.r - 0.25;
+    let q = x*x + c.i*c.i;
+    if q * (q + x) <= 0.25 * c.i*c.i {
+        return true;
+    }
+    (c.r + 1.0)*(c.r + 1.0) + c.i*c.i <= 1.0/16.0
+}
+
+// Final state of a sample's orbit
+#[derive(Copy, Clone)]
+struct Orbit {
+    iterations: u32,
+    // Iterations actually computed, the cardioid and periodicity checks skip the rest
+    computed: u32,
+    escaped: bool,
+    z: Complex,
+    // Derivative of z with respect to the sample position, only tracked for distance estimation
+    dz: Complex,
+    // Closest distance of the orbit to the trap, only tracked for orbit trap coloring
+    trap_distance: Real,
+    // Distance from the sample to the boundary of the set, only estimated inside it for InteriorColoring::Distance
+    interior_distance: Option<Real>,
+}
+
+// Follows Newton's method from pos, or the Nova iteration of pos, until the steps get
+// shorter than NEWTON_TOLERANCE, escaped means converged for these orbits
+fn iterate_newton(desc: &ThreadDescryptor, pos: &Complex) -> Orbit {
+    // At the critical point z = 1 of z^d - 1 the first step only adds the pixel
+    let mut z = match desc.fractal {
+        FractalKind::Nova {..} => Complex {r: 1.0, i: 0.0},
+        _ => *pos,
+    };
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < desc.max_iterations {
+        let step = match desc.fractal {
+            FractalKind::Nova {relax} => nova_step(&z, pos, relax, desc.exponent),
+            _ => newton_step(&z, NEWTON_DEGREE),
+        };
+        z = z - step;
+        iterations += 1;
+        // The derivative vanishes at 0, such orbits never converge
+        if !step.length_squared().is_finite() {
+            break;
+        }
+        if step.length_squared() <= NEWTON_TOLERANCE {
+            converged = true;
+            break;
+        }
+    }
+    let zero = Complex {r: 0.0, i: 0.0};
+    Orbit {iterations, computed: iterations, escaped: converged, z, dz: zero, trap_distance: Real::INFINITY, interior_distance: None}
+}
+
+// Iterates the recurrence for the sample at pos
+fn iterate(desc: &ThreadDescryptor, pos: &Complex) -> Orbit {
+    if let FractalKind::Newton | FractalKind::Nova {..} = desc.fractal {
+        return iterate_newton(desc, pos);
+    }
+    let (mut z, c) = match desc.fractal {
+        FractalKind::Julia {c} => (*pos, c),
+        _ => (Complex {r: 0.0, i: 0.0}, *pos),
+    };
+    // For Julia sets the derivative is taken with respect to z0, otherwise with respect to c
+    let (mut dz, dc) = match desc.fractal {
+        FractalKind::Julia {..} => (Complex {r: 1.0, i: 0.0}, Complex {r: 0.0, i: 0.0}),
+        _ => (Complex {r: 0.0, i: 0.0}, Complex {r: 1.0, i: 0.0}),
+    };
+    let track_derivative = matches!(desc.coloring, ColoringMode::Distance | ColoringMode::Slope);
+    let trap = match desc.coloring {
+        ColoringMode::OrbitTrap {trap} => Some(trap),
+        _ => None,
+    };
+    let mut trap_distance = Real::INFINITY;
+
+    let mut iterations: u32 = 0;
+    // Orbit traps and interior coloring color the interior too, so those orbits still have to be followed
+    if let FractalKind::Mandelbrot = desc.fractal {
+        if desc.exponent == 2 && trap.is_none() && desc.interior == InteriorColoring::Flat && in_main_cardioid_or_bulb(pos) {
+            iterations = desc.max_iterations;
+        }
+    }
+    // Periodicity checking: z is compared against a reference point that is moved
+    // forward after every check interval, and the interval doubles each time so
+    // cycles of any length are eventually caught. The comparison is exact, the
+    // iteration is deterministic so an orbit that repeats a value never escapes
+    let mut reference = z;
+    let mut check_interval = PERIODICITY_CHECK_INTERVAL;
+    let mut steps_since_reference = 0;
+    let bailout_squared = desc.bailout*desc.bailout;
+    let mut computed = 0;
+    while z.length_squared() <= bailout_squared && iterations < desc.max_iterations {
+        if track_derivative {
+            // dz = d*z^(d-1)*dz + dc
+            let d = Complex {r: desc.exponent as Real, i: 0.0};
+            dz = d * z.powi(desc.exponent.saturating_sub(1)) * dz + dc;
+        }
+        z = desc.fractal.step(&z, &c, desc.exponent);
+        iterations += 1;
+        computed += 1;
+        if let Some(trap) = trap {
+            trap_distance = trap_distance.min(trap.distance(&z));
+        }
+
+        if z == reference {
+            iterations = desc.max_iterations;
+            break;
+        }
+        steps_since_reference += 1;
+        if steps_since_reference == check_interval {
+            reference = z;
+            steps_since_reference = 0;
+            check_interval *= 2;
+        }
+    }
+    let escaped = z.length_squared() > bailout_squared;
+    let interior_distance = match (escaped, desc.interior) {
+        // Orbits that haven't settled on a cycle by the last iteration are close to the boundary
+        (false, InteriorColoring::Distance) => Some(interior_distance(&z, &c).unwrap_or(0.0)),
+        _ => None,
+    };
+    Orbit {iterations, computed, escaped, z, dz, trap_distance, interior_distance}
+}
+
+// Distance estimate from c inside the Mandelbrot set to its boundary, z is the end of its orbit
+// close to the attracting cycle. The period p of the cycle is found first and a point z0 of it
+// refined by Newton's method, the distance is (1 - |dz|^2) / |dzdc + dzdz*dc/(1 - dz)| with the
+// derivatives of z^p over one period from z0. None if the orbit hasn't settled on a cycle
+fn interior_distance(z: &Complex, c: &Complex) -> Option<Real> {
+    let mut w = *z;
+    let period = (1..=MAX_INTERIOR_PERIOD).find(|_| {
+        w = w*w + *c;
+        (w - *z).length_squared() < INTERIOR_PERIOD_TOLERANCE
+    })?;
+
+    let zero = Complex {r: 0.0, i: 0.0};
+    let one = Complex {r: 1.0, i: 0.0};
+    let two = Complex {r: 2.0, i: 0.0};
+    // Newton's method on z^p(z0) - z0
+    let mut z0 = *z;
+    for _ in 0..INTERIOR_NEWTON_STEPS {
+        let (mut w, mut dw) = (z0, one);
+        for _ in 0..period {
+            dw = two * w * dw;
+            w = w*w + *c;
+        }
+        z0 = z0 - (w - z0) / (dw - one);
+    }
+
+    let (mut w, mut dz, mut dc, mut dzdz, mut dzdc) = (z0, one, zero, zero, zero);
+    for _ in 0..period {
+        dzdz = two * (dz*dz + w*dzdz);
+        dzdc = two * (dz*dc + w*dzdc);
+        dz = two * w * dz;
+        dc = two * w * dc + one;
+        w = w*w + *c;
+    }
+    // A cycle that doesn't attract belongs to a point of the boundary or z hasn't reached it
+    let attraction = dz.length_squared();
+    if attraction >= 1.0 {
+        return None;
+    }
+    let distance = (1.0 - attraction) / (dzdc + dzdz * dc / (one - dz)).length();
+    distance.is_finite().then_some(distance)
+}
+
+// Palette color of an escaped orbit at its continuous iteration count
+fn smooth_color(desc: &ThreadDescryptor, palette: &Palette, orbit: &Orbit) -> Color {
+    cyclic_palette_color(desc, palette, desc.color_scale.apply(continuous_iterations(desc, orbit)) * desc.color_density)
+}
+
+// Continuous iteration count of an escaped orbit, nu = n + 1 - log_d(log|z|)
+fn continuous_iterations(desc: &ThreadDescryptor, orbit: &Orbit) -> Real {
+    if let FractalKind::Newton | FractalKind::Nova {..} = desc.fractal {
+        return orbit.iterations as Real;
+    }
+    let exponent = if desc.exponent > 1 { desc.exponent as Real } else { 2.0 };
+    (orbit.iterations as Real) + 1.0 - orbit.z.length().ln().ln() / exponent.ln()
+}
+
+// Color at a continuous palette position shifted by the palette offset, wrapping around past the last entry
+fn cyclic_palette_color(desc: &ThreadDescryptor, palette: &Palette, position: Real) -> Color {
+    palette.cycle(position + desc.palette_offset)
+}
+
+// Palette entries evenly spread over the palette for the basin of every root,
+// darkened the longer the orbit took to converge
+fn newton_color(desc: &ThreadDescryptor, palette: &Palette, orbit: &Orbit) -> Color {
+    if !orbit.escaped {
+        return desc.inside_color;
+    }
+    // The roots of z^n - 1 sit at the angles 2*pi*k/n
+    let sector = std::f64::consts::TAU as Real / (NEWTON_DEGREE as Real);
+    let root = (orbit.z.to_polar().1 / sector).round().rem_euclid(NEWTON_DEGREE as Real) as usize;
+    let base = palette[root * palette.len() / (NEWTON_DEGREE as usize)];
+    let shade = NEWTON_SHADE.powi(orbit.iterations as i32);
+    Color::rgba(base.r * shade, base.g * shade, base.b * shade, base.a)
+}
+
+// Colors a single sample from its orbit
+// cdf maps an iteration count to its cumulative share of the escaped pixels, only used for histogram coloring
+fn sample_color(desc: &ThreadDescryptor, palette: &Palette, cdf: &[Real], orbit: &Orbit) -> Color {
+    if let FractalKind::Newton = desc.fractal {
+        return newton_color(desc, palette, orbit);
+    }
+    if !orbit.escaped {
+        let palette_size = palette.len() as Real;
+        match desc.interior {
+            InteriorColoring::Flat => {}
+            InteriorColoring::Magnitude => return cyclic_palette_color(desc, palette, orbit.z.length() / desc.bailout * palette_size),
+            InteriorColoring::Angle => {
+                let turns = orbit.z.to_polar().1 / (std::f64::consts::TAU as Real) + 0.5;
+                return cyclic_palette_color(desc, palette, turns * palette_size);
+            }
+            InteriorColoring::Distance => {
+                if let Some(distance) = orbit.interior_distance {
+                    let pixel_size = desc.view_size.x / (desc.color_buffer_size.x as Real);
+                    let glow = (-distance / (INTERIOR_GLOW_WIDTH * pixel_size)).exp();
+                    return Color::lerp(desc.inside_color, palette.brightest(), glow);
+                }
+            }
+        }
+    }
+    match desc.coloring {
+        ColoringMode::Banded => {
+            if !orbit.escaped {
+                return desc.inside_color;
+            }
+            let position = (desc.color_scale.apply(orbit.iterations as Real) * desc.color_density + desc.palette_offset).rem_euclid(palette.len() as Real);
+            palette[position as usize % palette.len()]
+        }
+        ColoringMode::Smooth => {
+            if !orbit.escaped {
+                return desc.inside_color;
+            }
+            smooth_color(desc, palette, orbit)
+        }
+        ColoringMode::Distance => {
+            if !orbit.escaped {
+                return desc.inside_color;
+            }
+            // Exterior distance estimate |z|*log|z| / |dz|, measured in pixels
+            let length = orbit.z.length();
+            let distance = length * length.ln() / orbit.dz.length();
+            let pixel_size = desc.view_size.x / (desc.color_buffer_size.x as Real);
+            let glow = 1.0 - clamp(distance / (DISTANCE_GLOW_WIDTH * pixel_size), 0.0, 1.0);
+            let glow = if glow.is_nan() { 1.0 } else { glow };
+            Color::lerp(desc.inside_color, smooth_color(desc, palette, orbit), glow.sqrt())
+        }
+        ColoringMode::Histogram => {
+            if !orbit.escaped {
+                return desc.inside_color;
+            }
+            palette.sample(cdf[orbit.iterations as usize])
+        }
+        ColoringMode::Slope => {
+            if !orbit.escaped {
+                return desc.inside_color;
+            }
+            // The normal points along z/dz, away from the set
+            let u = orbit.z * orbit.dz.conjugate();
+            let length = u.length();
+            let intensity = if length > 0.0 { desc.light.intensity(&Complex {r: u.r / length, i: u.i / length}) } else { 1.0 };
+            let mut color = smooth_color(desc, palette, orbit);
+            color.r *= intensity;
+            color.g *= intensity;
+            color.b *= intensity;
+            color
+        }
+        ColoringMode::Grayscale => {
+            // The gray ramp ends in a black set whatever the inside color
+            if !orbit.escaped {
+                return INTERIOR_COLOR;
+            }
+            let gray = 1.0 - clamp(continuous_iterations(desc, orbit) / (desc.max_iterations as Real), 0.0, 1.0);
+            Color::rgb(gray, gray, gray)
+        }
+        ColoringMode::OrbitTrap {..} => {
+            if orbit.trap_distance.is_infinite() {
+                return desc.inside_color;
+            }
+            cyclic_palette_color(desc, palette, orbit.trap_distance * TRAP_PALETTE_SCALE)
+        }
+    }
+}
+
+// Offset in [-0.5:0.5] of a sample from its pixel center
+fn sample_offset<R: Rng>(desc: &ThreadDescryptor, sample: usize, rng: &mut R) -> Vec2<Real> {
+    match desc.sampling {
+        SamplingMode::Random => Vec2::<Real>{x: rng.gen_range(-0.5, 0.5), y: rng.gen_range(-0.5, 0.5)},
+        SamplingMode::Center => Vec2::<Real>::new(),
+        SamplingMode::Stratified => {
+            // Samples past k*k wrap around and land in the first cells again
+            let k = ((desc.sample_count as Real).sqrt().round() as usize).max(1);
+            let cell = sample % (k * k);
+            let cell_x = (cell % k) as Real;
+            let cell_y = (cell / k) as Real;
+            Vec2::<Real>{
+                x: (cell_x + rng.gen_range(0.0, 1.0)) / (k as Real) - 0.5,
+                y: (cell_y + rng.gen_range(0.0, 1.0)) / (k as Real) - 0.5,
+            }
+        }
+    }
+}
+
+// Point of the complex plane at the given offset from the center of pixel (x, y)
+fn pixel_position(desc: &ThreadDescryptor, x: usize, y: usize, jitter: Vec2<Real>) -> Complex {
+    let mut norm_pos = Vec2::<Real>::new();
+    norm_pos.x = ((x as Real) + jitter.x)/(desc.color_buffer_size.x as Real) * 2.0 - 1.0; // [-1:1]
+    norm_pos.y = -(((y as Real) + jitter.y)/(desc.color_buffer_size.y as Real) * 2.0 - 1.0); // [-1:1]
+    let mut pos = Complex {r: 0.0, i: 0.0};
+    pos.r = desc.center.x + norm_pos.x * desc.view_size.x / 2.0; // real axis
+    pos.i = desc.center.y + norm_pos.y * desc.view_size.y / 2.0; // imaginary axis
+    pos
+}
+
+// Renders one tile, rows holds the tile's slice of every buffer row it covers
+// and pixels_done counts every finished pixel for the progress bar. Returns the
+// samples and iterations computed for all of its pixels
+fn thread_worker<R: Rng>(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, palette: &Palette, prepass: &Prepass, pixels_done: &AtomicUsize, rng: &mut R) -> Work {
+    if desc.debug_tiles {
+        return fill_debug_tile(rows, desc, pixels_done);
+    }
+    if let Some(center) = &prepass.center {
+        return bignum::thread_worker(rows, desc, center, palette, prepass, pixels_done, rng);
+    }
+    if desc.deep_zoom {
+        return perturbation::thread_worker(rows, desc, palette, prepass, pixels_done, rng);
+    }
+    #[cfg(feature = "simd")]
+    if simd::supports(desc) && prepass.samples.is_empty() {
+        return simd::thread_worker(rows, desc, palette, &prepass.cdf, pixels_done, rng);
+    }
+
+    // Render the fractal directly into the tile rows
+    shade_rows(rows, desc, palette, prepass, pixels_done, rng, |x, y, jitter| iterate(desc, &pixel_position(desc, x, y, jitter)))
+}
+
+// Fills the tile rows with the color of the tile index: hues a golden angle apart, so tiles
+// close in the queue differ, and a checkerboard of brightness between neighbors
+fn fill_debug_tile(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, pixels_done: &AtomicUsize) -> Work {
+    let position = desc.tile_position();
+    let value = match (position.x + position.y) % 2 {
+        0 => 1.0,
+        _ => 0.5,
+    };
+    let color = Color::from_hsv(137.5 * desc.tile_index() as Real, 0.8, value);
+    let mut work = Work::default();
+    for row in rows.iter_mut() {
+        row.fill(color);
+        work.pixels += row.len() as u64;
+        pixels_done.fetch_add(row.len(), Ordering::Relaxed);
+    }
+    work
+}
+
+// Colors every pixel of the tile rows from the orbits of its samples, orbit(x, y, jitter) iterates
+// the sample at the given offset from the center of image pixel (x, y). With an adaptive threshold the
+// first ADAPTIVE_SAMPLES samples are spread evenly over the sample indices, so stratified samples cover
+// the pixel, and the others are only taken where the variance of their colors is above the threshold
+fn shade_rows<R: Rng, F: Fn(usize, usize, Vec2<Real>) -> Orbit>(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, palette: &Palette, prepass: &Prepass, pixels_done: &AtomicUsize, rng: &mut R, orbit: F) -> Work {
+    // Color of the pixel at x, y of the tile and the work it took
+    let mut shade_pixel = |x: usize, y: usize| {
+        let mut work = Work {pixels: 1, ..Work::default()};
+        // Detail sampling spreads the samples of a pixel as if it was the sample count of the render
+        let mut pixel_desc = *desc;
+        if let Some(samples) = prepass.pixel_samples(x+desc.offset.x, y+desc.offset.y) {
+            pixel_desc.sample_count = samples;
+        }
+        let desc = &pixel_desc;
+        let first_pass = match desc.adaptive_threshold {
+            Some(_) => min(ADAPTIVE_SAMPLES, desc.sample_count),
+            None => desc.sample_count,
+        };
+        // Iteration count and escape of the samples for fast fill, None once two of them differ
+        let mut band = None;
+        let mut same_band = true;
+        let mut shade = |sample: usize| {
+            let jitter = sample_offset(desc, sample, rng);
+            let orbit = orbit(x+desc.offset.x, y+desc.offset.y, jitter);
+            work.samples += 1;
+            work.iterations += orbit.computed as u64;
+            same_band &= *band.get_or_insert((orbit.iterations, orbit.escaped)) == (orbit.iterations, orbit.escaped);
+            sample_color(desc, palette, &prepass.cdf, &orbit)
+        };
+        // Stochastic Sampling
+        let mut pixel_color = Color::new();
+        let mut squares = [0.0; 3];
+        for i in 0..first_pass {
+            let color = shade(i * desc.sample_count / first_pass);
+            pixel_color = pixel_color + color;
+            for (square, channel) in squares.iter_mut().zip([color.r, color.g, color.b]) {
+                *square += channel*channel;
+            }
+        }
+        let mut taken = first_pass;
+
+        let mean = pixel_color / first_pass as Real;
+        let variance: Real = squares.iter().zip([mean.r, mean.g, mean.b]).map(|(square, mean)| square / first_pass as Real - mean*mean).sum();
+        if desc.adaptive_threshold.is_some_and(|threshold| variance > threshold) {
+            // The samples between two of the first pass
+            for i in 0..first_pass {
+                for sample in i * desc.sample_count / first_pass + 1..(i + 1) * desc.sample_count / first_pass {
+                    pixel_color = pixel_color + shade(sample);
+                    taken += 1;
+                }
+            }
+        }
+        (pixel_color / taken as Real, work, band.filter(|_| same_band))
+    };
+
+    if desc.fast_fill {
+        return fill_rectangles(rows, desc, pixels_done, shade_pixel);
+    }
+    let mut work = Work::default();
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            let (color, pixel_work, _) = shade_pixel(x, y);
+            *pixel = color;
+            work = work + pixel_work;
+        }
+        pixels_done.fetch_add(row.len(), Ordering::Relaxed);
+    }
+    work
+}
+
+// Mariani-Silver subdivision of the tile rows: the pixels on the border of a rectangle are rendered
+// and if all of their samples escape at the same iteration, the inside is filled with the mean color
+// of the border. Otherwise the rectangle is split in two along its longer side, sharing the middle line,
+// until it is small enough to render outright. shade_pixel(x, y) renders pixel x, y of the tile and
+// gives the iteration count and escape its samples share
+fn fill_rectangles<S>(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, pixels_done: &AtomicUsize, mut shade_pixel: S) -> Work
+    where S: FnMut(usize, usize) -> (Color, Work, Option<(u32, bool)>) {
+    let width = desc.thread_size.x;
+    let height = desc.thread_size.y;
+    let mut work = Work::default();
+    // Band of the samples of every border pixel rendered so far, None where they differ
+    let mut shaded = vec![false; width * height];
+    let mut bands: Vec<Option<(u32, bool)>> = vec![None; width * height];
+    let mut rectangles = vec![(0, 0, width, height)];
+    while let Some((x0, y0, w, h)) = rectangles.pop() {
+        let mut border: Vec<(usize, usize)> = (x0..x0 + w).flat_map(|x| [(x, y0), (x, y0 + h - 1)]).collect();
+        border.extend((y0 + 1..y0 + h - 1).flat_map(|y| [(x0, y), (x0 + w - 1, y)]));
+        let mut first = None;
+        let mut uniform = true;
+        let mut border_color = Color::new();
+        for &(x, y) in &border {
+            if !shaded[y * width + x] {
+                let (color, pixel_work, band) = shade_pixel(x, y);
+                rows[y][x] = color;
+                work = work + pixel_work;
+                pixels_done.fetch_add(1, Ordering::Relaxed);
+                shaded[y * width + x] = true;
+                bands[y * width + x] = band;
+            }
+            let band = bands[y * width + x];
+            uniform &= band.is_some() && *first.get_or_insert(band) == band;
+            border_color = border_color + rows[y][x];
+        }
+        if w <= 2 || h <= 2 {
+            continue;
+        }
+
+        let inside = (w - 2) * (h - 2);
+        if uniform {
+            // The border is anti-aliased like the rest of the image, its mean hides the seam
+            let color = border_color / border.len() as Real;
+            for row in &mut rows[y0 + 1..y0 + h - 1] {
+                row[x0 + 1..x0 + w - 1].fill(color);
+            }
+            work.pixels += inside as u64;
+            pixels_done.fetch_add(inside, Ordering::Relaxed);
+        }
+        else if w <= FILL_MIN_SIZE && h <= FILL_MIN_SIZE {
+            for (y, row) in rows.iter_mut().enumerate().take(y0 + h - 1).skip(y0 + 1) {
+                for (x, pixel) in row.iter_mut().enumerate().take(x0 + w - 1).skip(x0 + 1) {
+                    let (color, pixel_work, _) = shade_pixel(x, y);
+                    *pixel = color;
+                    work = work + pixel_work;
+                }
+            }
+            pixels_done.fetch_add(inside, Ordering::Relaxed);
+        }
+        else if w >= h {
+            rectangles.push((x0, y0, w / 2 + 1, h));
+            rectangles.push((x0 + w / 2, y0, w - w / 2, h));
+        }
+        else {
+            rectangles.push((x0, y0, w, h / 2 + 1));
+            rectangles.push((x0, y0 + h / 2, w, h - h / 2));
+        }
+    }
+    work
+}
+
+// Results of the passes over the pixel centers that have to be done before any tile is rendered
+struct Prepass {
+    // Share of the escaped pixels by iteration count for histogram coloring, see escape_cdf
+    cdf: Vec<Real>,
+    // Samples of every pixel of region in row major order for detail sampling, see detail_samples
+    samples: Vec<usize>,
+    region: Region,
+    // Center of the view in fixed point for arbitrary precision, see bignum::view_center
+    center: Option<bignum::BigComplex>,
+}
+
+impl Prepass {
+    fn new(config: &RenderConfig) -> Prepass {
+        let cdf = match config.coloring {
+            ColoringMode::Histogram => escape_cdf(config),
+            _ => Vec::new(),
+        };
+        let samples = match config.detail_threshold {
+            Some(threshold) => detail_samples(config, threshold),
+            None => Vec::new(),
+        };
+        let center = config.precision.map(|bits| bignum::view_center(config, bits));
+        Prepass {cdf, samples, region: config.output_region(), center}
+    }
+
+    // Samples detail sampling takes at pixel x, y of the image
+    fn pixel_samples(&self, x: usize, y: usize) -> Option<usize> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples[(y - self.region.y) * self.region.width + x - self.region.x])
+    }
+}
+
+// Whether workers get their own threads, wasm32 renders on the calling thread
+const SPAWN_WORKERS: bool = !cfg!(target_arch = "wasm32");
+
+// Number of worker threads to use for config
+fn worker_count(config: &RenderConfig) -> usize {
+    if !SPAWN_WORKERS {
+        return 1;
+    }
+    match config.thread_count {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    }
+}
+
+// Iterates the center of every pixel and returns the row major buffer of value(orbit)
+fn map_pixel_centers<T, F>(config: &RenderConfig, value: F) -> Vec<T>
+where
+    T: Copy + Default + Send,
+    F: Fn(&ThreadDescryptor, &Orbit) -> T + Sync,
+{
+    let desc = ThreadDescryptor::from_config(config);
+    let region = config.output_region();
+    // Deep zooms share one reference orbit picked over the whole image
+    let reference = match config.deep_zoom {
+        true => {
+            let mut whole_image = desc;
+            whole_image.thread_size = desc.color_buffer_size;
+            Some(perturbation::ReferenceOrbit::longest(&whole_image))
+        }
+        false => None,
+    };
+    let center = config.precision.map(|bits| bignum::view_center(config, bits));
+    let mut values = vec![T::default(); region.width * region.height];
+
+    let queue = Mutex::new(values.chunks_mut(region.width.max(1)).enumerate());
+    thread::scope(|scope| {
+        for _ in 0..min(region.height, worker_count(config)) {
+            let queue = &queue;
+            let desc = &desc;
+            let value = &value;
+            let reference = &reference;
+            let center = &center;
+            let worker = move || loop {
+                let next_row = queue.lock().unwrap().next();
+                match next_row {
+                    Some((y, row)) => {
+                        for (x, pixel) in row.iter_mut().enumerate() {
+                            let (x, y) = (region.x + x, region.y + y);
+                            let orbit = match (center, reference) {
+                                (Some(center), _) => bignum::iterate(desc, center, x as f64, y as f64),
+                                (None, Some(reference)) => reference.iterate(desc, x as f64, y as f64),
+                                (None, None) => iterate(desc, &pixel_position(desc, x, y, Vec2::<Real>::new())),
+                            };
+                            *pixel = value(desc, &orbit);
+                        }
+                    }
+                    None => break,
+                }
+            };
+            if SPAWN_WORKERS {
+                scope.spawn(worker);
+            }
+            else {
+                worker();
+            }
+        }
+    });
+    values
+}
+
+// First pass of histogram coloring: returns, for each iteration count, the share
+// of the escaped pixel centers that escaped within that many iterations
+fn escape_cdf(config: &RenderConfig) -> Vec<Real> {
+    // The histogram always covers the whole image, so the colors of regions match
+    let mut config = config.clone();
+    config.region = None;
+    // Interior pixels are left out of the histogram
+    let iterations = map_pixel_centers(&config, |desc, orbit| if orbit.escaped { orbit.iterations } else { desc.max_iterations + 1 });
+
+    let mut histogram = vec![0usize; config.max_iterations as usize + 1];
+    for &count in iterations.iter().filter(|&&count| count <= config.max_iterations) {
+        histogram[count as usize] += 1;
+    }
+    let total: usize = histogram.iter().sum();
+    let mut escaped = 0;
+    histogram.iter().map(|&count| {
+        escaped += count;
+        if total == 0 { 0.0 } else { (escaped as Real) / (total as Real) }
+    }).collect()
+}
+
+// ln(1 + continuous iterations) of an orbit, max_iterations inside the set. The logarithm keeps the
+// bands far from the set, which differ by single iterations, close together
+fn escape_level(desc: &ThreadDescryptor, orbit: &Orbit) -> Real {
+    let iterations = if orbit.escaped { continuous_iterations(desc, orbit) } else { desc.max_iterations as Real };
+    iterations.max(0.0).ln_1p()
+}
+
+// First pass of detail sampling: the samples of every pixel of the rendered region, the full sample
+// count times the largest escape level difference of the pixel center to its 8 neighbors over threshold
+fn detail_samples(config: &RenderConfig, threshold: Real) -> Vec<usize> {
+    let levels = map_pixel_centers(config, escape_level);
+    let region = config.output_region();
+    let (width, height) = (region.width as isize, region.height as isize);
+    // Pixels past the border repeat the border pixels
+    let level = |x: isize, y: isize| levels[(clamp(y, 0, height - 1) * width + clamp(x, 0, width - 1)) as usize];
+    let mut samples = Vec::with_capacity(levels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let mut gradient: Real = 0.0;
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                gradient = gradient.max((level(x + dx, y + dy) - level(x, y)).abs());
+            }
+            let wanted = (gradient / threshold * config.sample_count as Real).ceil() as usize;
+            samples.push(clamp(wanted, 1, config.sample_count));
+        }
+    }
+    samples
+}
+
+// Continuous iteration count of every pixel center, -1 inside the set, for coloring the image elsewhere
+pub fn render_iterations(config: &RenderConfig) -> Vec<f32> {
+    map_pixel_centers(config, |desc, orbit| if orbit.escaped { continuous_iterations(desc, orbit) as f32 } else { -1.0 })
+}
+
+// Renders the fractal described by config and returns the row major color buffer
+pub fn render_mandelbrot(config: &RenderConfig) -> Vec<Color> {
+    render_mandelbrot_interruptible(config, &AtomicBool::new(false))
+}
+
+// Renders config to tightly packed 8-bit RGBA rows of its output region, sRGB encoded if
+// config.gamma, for showing the image without saving it
+pub fn render_to_rgba(config: &RenderConfig) -> Vec<u8> {
+    let region = config.output_region();
+    to_rgba8(&render_mandelbrot(config), region.width, region.height, config.gamma)
+}
+
+// Same as render_mandelbrot, but stops starting new work once interrupted is set
+// and returns the partially rendered buffer, unrendered pixels are left transparent
+pub fn render_mandelbrot_interruptible(config: &RenderConfig, interrupted: &AtomicBool) -> Vec<Color> {
+    render_mandelbrot_with_progress(config, interrupted, |_| {})
+}
+
+// Same as render_mandelbrot_interruptible, and reports the rendered fraction in [0:1] to progress.
+// progress is only called from the calling thread, as work completes and a few times per second
+pub fn render_mandelbrot_with_progress<F: FnMut(f32)>(config: &RenderConfig, interrupted: &AtomicBool, mut progress: F) -> Vec<Color> {
+    render_counted(config, interrupted, &mut progress).0
+}
+
+// Work done by a render
+#[derive(Copy, Clone, Debug)]
+pub struct RenderStats {
+    // Pixels rendered, a resumed render leaves out the ones of the checkpoint
+    pub pixels: usize,
+    // Samples of all pixels, or Buddhabrot orbits
+    pub samples: u64,
+    // Iterations of every sample of every pixel, or of every Buddhabrot orbit
+    pub iterations: u64,
+    pub elapsed: time::Duration,
+}
+
+impl RenderStats {
+    fn new(work: Work, start_time: time::Instant) -> RenderStats {
+        RenderStats {pixels: work.pixels as usize, samples: work.samples, iterations: work.iterations, elapsed: start_time.elapsed()}
+    }
+}
+
+// Work done by the tiles rendered so far, summed into RenderStats
+#[derive(Copy, Clone, Default)]
+struct Work {
+    pixels: u64,
+    samples: u64,
+    iterations: u64,
+}
+
+impl ops::Add for Work {
+    type Output = Work;
+
+    fn add(self, rhs: Work) -> Work {
+        Work {pixels: self.pixels + rhs.pixels, samples: self.samples + rhs.samples, iterations: self.iterations + rhs.iterations}
+    }
+}
+
+// Same as render_mandelbrot_interruptible, and also returns how much work the render took
+pub fn render_mandelbrot_with_stats(config: &RenderConfig, interrupted: &AtomicBool) -> (Vec<Color>, RenderStats) {
+    let start_time = time::Instant::now();
+    let (color_buffer, work) = render_counted(config, interrupted, &mut |_| {});
+    (color_buffer, RenderStats::new(work, start_time))
+}
+
+// Renders config and returns the buffer along with the work it took
+fn render_counted(config: &RenderConfig, interrupted: &AtomicBool, progress: &mut dyn FnMut(f32)) -> (Vec<Color>, Work) {
+    if let FractalKind::Buddhabrot = config.fractal {
+        // Orbits cross the whole image, the region is cut out of the full render
+        let (color_buffer, iterations) = buddhabrot::render_buddhabrot(config, interrupted, progress);
+        let region = config.output_region();
+        let cropped = color_buffer.chunks(config.width).skip(region.y).take(region.height)
+            .flat_map(|row| row[region.x..region.x + region.width].iter().copied())
+            .collect();
+        let pixels = (region.width * region.height) as u64;
+        let work = Work {pixels, samples: (config.width * config.height * config.sample_count) as u64, iterations};
+        return (cropped, work);
+    }
+    render_tiles(config, interrupted, None, progress)
+}
+
+// Same as render_mandelbrot_interruptible, but only renders the tiles the checkpoint is
+// missing and records every finished tile in it. save is called with the checkpoint
+// every CHECKPOINT_INTERVAL and once more if the render stops before every tile is done.
+// The finished image is checkpoint.color_buffer, the Buddhabrot isn't tiled and can't be resumed
+pub fn render_mandelbrot_resumable<F: FnMut(&Checkpoint)>(config: &RenderConfig, interrupted: &AtomicBool, checkpoint: &mut Checkpoint, mut save: F) -> RenderStats {
+    let start_time = time::Instant::now();
+    let (_, work) = render_tiles(config, interrupted, Some((checkpoint, &mut save)), &mut |_| {});
+    RenderStats::new(work, start_time)
+}
+
+// Number of tiles the image of config is split into
+fn tile_count(config: &RenderConfig) -> usize {
+    let region = config.output_region();
+    divide_roundup(region.width, config.tile_size) * divide_roundup(region.height, config.tile_size)
+}
+
+// Indices of the row major descriptors in the order of config.tile_order, measured in
+// tiles from the center of the rendered region
+fn tile_queue_order(config: &RenderConfig, descryptors: &[ThreadDescryptor]) -> Vec<usize> {
+    let region = config.output_region();
+    let tile_size = config.tile_size as Real;
+    let key = |desc: &ThreadDescryptor| {
+        let x = ((desc.offset.x - region.x) as Real + desc.thread_size.x as Real / 2.0 - region.width as Real / 2.0) / tile_size;
+        let y = ((desc.offset.y - region.y) as Real + desc.thread_size.y as Real / 2.0 - region.height as Real / 2.0) / tile_size;
+        match config.tile_order {
+            TileOrder::Row => (0.0, 0.0),
+            // Rings are squares of tiles, atan2 grows clockwise on the image as rows count down
+            TileOrder::Spiral => (x.abs().max(y.abs()).round(), (-y).atan2(-x)),
+            TileOrder::CenterOut => (x*x + y*y, 0.0),
+        }
+    };
+    let keys: Vec<(Real, Real)> = descryptors.iter().map(key).collect();
+    let mut order: Vec<usize> = (0..descryptors.len()).collect();
+    // A stable sort keeps tiles with equal keys in row major order
+    order.sort_by(|&a, &b| keys[a].0.total_cmp(&keys[b].0).then(keys[a].1.total_cmp(&keys[b].1)));
+    order
+}
+
+// Descryptors of the tiles of the rendered region in row major order. Tiles in the last
+// column and row only get the pixels that remain, e.g. 86 of a 1366 pixel row of 128 pixel tiles
+fn tile_descryptors(config: &RenderConfig) -> Vec<ThreadDescryptor> {
+    let region = config.output_region();
+    let tile_size = config.tile_size;
+    let mut threads_descryptors = Vec::new();
+    for y in 0..divide_roundup(region.height, tile_size) {
+        for x in 0..divide_roundup(region.width, tile_size) {
+            let mut new_desc = ThreadDescryptor::from_config(config);
+            new_desc.offset = Vec2::<usize>{x: region.x + x * tile_size, y: region.y + y * tile_size};
+            // x*tile_size < width as x < width/tile_size rounded up, so these never underflow
+            let remaining_width = region.width - x*tile_size;
+            let remaining_height = region.height - y*tile_size;
+            new_desc.thread_size = Vec2::<usize>{x: min(tile_size, remaining_width), y: min(tile_size, remaining_height)};
+            threads_descryptors.push(new_desc);
+        }
+    }
+    threads_descryptors
+}
+
+// Checkpoint of a resumable render and the callback saving it
+type CheckpointSink<'a> = (&'a mut Checkpoint, &'a mut dyn FnMut(&Checkpoint));
+
+// Renders the tiles of config, returns the buffer and the work done
+fn render_tiles(config: &RenderConfig, interrupted: &AtomicBool, mut checkpoint: Option<CheckpointSink>, progress: &mut dyn FnMut(f32)) -> (Vec<Color>, Work) {
+    // Tiles cover the rendered region, their offsets stay in whole image pixels
+    let region = config.output_region();
+    // Row major, a resumed render starts from the pixels of the finished tiles
+    let mut color_buffer = match &checkpoint {
+        Some((checkpoint, _)) => checkpoint.color_buffer.clone(),
+        None => vec![Color::new(); region.width * region.height],
+    };
+    let finished_before = match &checkpoint {
+        Some((checkpoint, _)) => checkpoint.finished_tiles.clone(),
+        None => vec![false; tile_count(config)],
+    };
+    let keep_tiles = checkpoint.is_some();
+
+    // The clock is only read for the progress bar and the checkpoint, wasm32 has none
+    let mut report = match config.progress {
+        true => Some(ProgressReport::start()),
+        false => None,
+    };
+
+    // Histogram coloring and detail sampling need the pixel centers of the whole image before any pixel is colored
+    let prepass = Prepass::new(config);
+
+    let palette = match config.decodes_srgb() {
+        true => config.palette.to_linear(),
+        false => config.palette.clone(),
+    };
+
+    let threads_descryptors = tile_descryptors(config);
+    let tile_size = config.tile_size;
+
+    // Split the buffer into disjoint tiles, each tile gets its slice of every row it covers
+    let tiles_per_row = divide_roundup(region.width, tile_size);
+    let mut tiles_rows: Vec<Vec<&mut [Color]>> = threads_descryptors.iter().map(|_| Vec::new()).collect();
+    for (y, row) in color_buffer.chunks_mut(region.width).enumerate() {
+        for (x, tile_row) in row.chunks_mut(tile_size).enumerate() {
+            tiles_rows[(y/tile_size) * tiles_per_row + x].push(tile_row);
+        }
+    }
+
+    // Workers pull the next unfinished tile from the queue until it is empty and report every
+    // finished tile with its iteration count and render time if verbose, along with a copy of
+    // its pixels when they have to go into the checkpoint
+    let tile_count = threads_descryptors.len();
+    let already_finished = finished_before.iter().filter(|&&finished| finished).count();
+    let thread_count = worker_count(config);
+    let mut pending: Vec<_> = threads_descryptors.iter().zip(tiles_rows).enumerate().filter(|(id, _)| !finished_before[*id]).collect();
+    let order = tile_queue_order(config, &threads_descryptors);
+    let mut rank = vec![0; tile_count];
+    for (position, &id) in order.iter().enumerate() {
+        rank[id] = position;
+    }
+    pending.sort_by_key(|(id, _)| rank[*id]);
+    let queue = Mutex::new(pending.into_iter());
+    let pixels_done = AtomicUsize::new(0);
+    let mut work = Work::default();
+    let pixels_before: usize = threads_descryptors.iter().zip(&finished_before)
+        .filter(|(_, &finished)| finished)
+        .map(|(desc, _)| desc.thread_size.x * desc.thread_size.y)
+        .sum();
+    let (sender, receiver) = channel::<(usize, Vec<Color>, Work, Option<time::Duration>)>();
+    thread::scope(|scope| {
+        for _ in 0..min(tile_count - already_finished, thread_count) {
+            let queue = &queue;
+            let prepass = &prepass;
+            let palette = &palette;
+            let pixels_done = &pixels_done;
+            let sender = sender.clone();
+            let worker = move || loop {
+                // Once interrupted no new tile is started, tiles in flight still finish
+                if interrupted.load(Ordering::SeqCst) {
+                    break;
+                }
+                let next_tile = queue.lock().unwrap().next();
+                match next_tile {
+                    Some((id, (descryptor, mut rows))) => {
+                        // Seeding per tile of the whole image keeps the output independent of the
+                        // thread count, and of the region when it is aligned to the tiles
+                        let tile_index = descryptor.tile_index();
+                        let tile_start = config.verbose.then(time::Instant::now);
+                        let tile_work = match config.seed {
+                            Some(seed) => thread_worker(&mut rows, descryptor, palette, prepass, pixels_done, &mut StdRng::seed_from_u64(seed ^ tile_index as u64)),
+                            None => thread_worker(&mut rows, descryptor, palette, prepass, pixels_done, &mut rand::thread_rng()),
+                        };
+                        let pixels = if keep_tiles { rows.iter().flat_map(|row| row.iter().copied()).collect() } else { Vec::new() };
+                        sender.send((id, pixels, tile_work, tile_start.map(|start| start.elapsed()))).unwrap();
+                    }
+                    None => break,
+                }
+            };
+            // Threads can't be spawned on wasm32, its single worker renders every tile
+            // on the calling thread before they are collected
+            if SPAWN_WORKERS {
+                scope.spawn(worker);
+            }
+            else {
+                worker();
+            }
+        }
+
+        // The channel closes once every worker has stopped, the progress bar is
+        // redrawn from the pixel counter between tiles too
+        drop(sender);
+        let mut last_save = checkpoint.as_ref().map(|_| time::Instant::now());
+        let mut finished_tiles = 0;
+        loop {
+            let message = match SPAWN_WORKERS {
+                true => receiver.recv_timeout(PROGRESS_INTERVAL),
+                false => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match message {
+                Ok((id, pixels, tile_work, elapsed)) => {
+                    finished_tiles += 1;
+                    if let Some(elapsed) = elapsed {
+                        print_tile(id, &threads_descryptors[id], &tile_work, elapsed);
+                    }
+                    work = work + tile_work;
+                    if let (Some((checkpoint, save)), Some(last_save)) = (&mut checkpoint, &mut last_save) {
+                        checkpoint.store_tile(id, &threads_descryptors[id], &pixels, &region);
+                        if last_save.elapsed() >= CHECKPOINT_INTERVAL {
+                            save(checkpoint);
+                            *last_save = time::Instant::now();
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            let pixels = pixels_before + pixels_done.load(Ordering::Relaxed);
+            progress(pixels as f32 / (region.width*region.height) as f32);
+            if let Some(report) = &mut report {
+                let percent = pixels*100/(region.width*region.height);
+                report.print(percent as u32, finished_tiles);
+            }
+        }
+    });
+
+    if let Some((checkpoint, save)) = checkpoint {
+        if !checkpoint.is_complete() {
+            save(checkpoint);
+        }
+    }
+    (color_buffer, work)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn powi_two_matches_squared_exactly() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let z = Complex {r: rng.gen_range(-2.0, 2.0), i: rng.gen_range(-2.0, 2.0)};
+            let a = z.powi(2);
+            let b = z.squared();
+            assert_eq!(a.r.to_bits(), b.r.to_bits());
+            assert_eq!(a.i.to_bits(), b.i.to_bits());
+        }
+    }
+
+    #[test]
+    fn polar_form_matches_rectangular_form() {
+        let close = |a: Complex, b: Complex| (a.r - b.r).abs() < 1e-4 && (a.i - b.i).abs() < 1e-4;
+        let pi = std::f64::consts::PI as Real;
+        assert!(close(Complex::from_polar(2.0, pi / 2.0), Complex {r: 0.0, i: 2.0}));
+        assert!(close(Complex::from_polar(1.0, pi), Complex {r: -1.0, i: 0.0}));
+
+        let z = Complex {r: -0.6, i: 0.8};
+        let (r, theta) = z.to_polar();
+        assert!((r - 1.0).abs() < 1e-6);
+        assert!(close(Complex::from_polar(r, theta), z));
+        // De Moivre: z^n has n times the argument and the n-th power of the magnitude
+        assert!(close(Complex::from_polar(r.powi(3), 3.0 * theta), z.powi(3)));
+        assert!(close(Complex::from_polar(r * 2.0, theta + pi / 2.0), z * Complex {r: 0.0, i: 2.0}));
+    }
+
+    #[test]
+    fn division_undoes_multiplication() {
+        let a = Complex {r: 1.5, i: -0.5};
+        let b = Complex {r: -0.25, i: 2.0};
+        let c = (a * b) / b;
+        assert!((c.r - a.r).abs() < 1e-5 && (c.i - a.i).abs() < 1e-5);
+    }
+
+    #[test]
+    fn complex_arithmetic_matches_known_values() {
+        let z = Complex {r: 3.0, i: -4.0};
+        let squared = z.squared();
+        assert_eq!((squared.r, squared.i), (-7.0, -24.0));
+        let sum = z.add(&Complex {r: -1.0, i: 1.5});
+        assert_eq!((sum.r, sum.i), (2.0, -2.5));
+        assert_eq!(z.length(), 5.0);
+        assert_eq!(z.length_squared(), 25.0);
+        assert_eq!(Complex {r: 0.0, i: 0.0}.length(), 0.0);
+    }
+
+    #[test]
+    fn clamp_keeps_values_within_bounds() {
+        assert_eq!(clamp(5, 0, 10), 5);
+        assert_eq!(clamp(0, 0, 10), 0);
+        assert_eq!(clamp(10, 0, 10), 10);
+        assert_eq!(clamp(-1, 0, 10), 0);
+        assert_eq!(clamp(11, 0, 10), 10);
+        assert_eq!(clamp(-3, 2, 2), 2);
+        assert_eq!(clamp(7, 2, 2), 2);
+        assert_eq!(clamp(0.5, 0.0, 1.0), 0.5);
+        assert_eq!(clamp(1.5, 0.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn divide_roundup_rounds_remainders_up() {
+        assert_eq!(divide_roundup(0, 4), 0);
+        assert_eq!(divide_roundup(8, 4), 2);
+        assert_eq!(divide_roundup(9, 4), 3);
+        assert_eq!(divide_roundup(11, 4), 3);
+        assert_eq!(divide_roundup(3, 4), 1);
+        assert_eq!(divide_roundup(5, 1), 5);
+    }
+
+    #[test]
+    fn min_returns_the_smaller_value() {
+        assert_eq!(min(1, 2), 1);
+        assert_eq!(min(2, 1), 1);
+        assert_eq!(min(3, 3), 3);
+        assert_eq!(min(-0.5, 0.25), -0.5);
+    }
+
+    #[test]
+    fn julia_sets_match_known_images() {
+        // For c = 0 the Julia set of every power is the unit circle
+        let mut config = RenderConfig::new();
+        config.width = 40;
+        config.height = 40;
+        config.center = Vec2::<Real>{x: 0.0, y: 0.0};
+        config.set_zoom(4.0);
+        config.fractal = FractalKind::Julia {c: Complex {r: 0.0, i: 0.0}};
+        for exponent in [2, 3, 5] {
+            config.exponent = exponent;
+            let desc = ThreadDescryptor::from_config(&config);
+            for y in 0..config.height {
+                for x in 0..config.width {
+                    let pos = pixel_position(&desc, x, y, Vec2::<Real>::new());
+                    if (pos.length() - 1.0).abs() > 0.05 {
+                        assert_eq!(iterate(&desc, &pos).escaped, pos.length() > 1.0, "{:?} with power {}", pos, exponent);
+                    }
+                }
+            }
+        }
+
+        // The basilica, c = -1: 0 cycles through -1, i escapes through -2 and 3
+        config.fractal = FractalKind::Julia {c: Complex {r: -1.0, i: 0.0}};
+        config.exponent = 2;
+        let desc = ThreadDescryptor::from_config(&config);
+        assert!(!iterate(&desc, &Complex {r: 0.0, i: 0.0}).escaped);
+        assert!(!iterate(&desc, &Complex {r: -1.0, i: 0.0}).escaped);
+        let orbit = iterate(&desc, &Complex {r: 0.0, i: 1.0});
+        assert!(orbit.escaped && orbit.iterations == 2);
+    }
+
+    #[test]
+    fn nova_converges_to_a_fixed_point() {
+        let mut config = RenderConfig::new();
+        config.fractal = FractalKind::Nova {relax: 0.8};
+        config.exponent = 3;
+        let desc = ThreadDescryptor::from_config(&config);
+        let orbit = iterate(&desc, &Complex {r: 0.0, i: 0.0});
+        assert!(orbit.escaped && orbit.iterations == 1);
+        let c = Complex {r: 0.1, i: -0.05};
+        let orbit = iterate(&desc, &c);
+        assert!(orbit.escaped);
+        let residual = nova_step(&orbit.z, &c, 0.8, 3);
+        assert!(residual.length() < 1e-4, "{:?}", residual);
+    }
+
+    // Douady rabbit, c of an attracting cycle of period 3 outside the cardioid and the period-2 bulb
+    const RABBIT: Complex = Complex {r: -0.1226, i: 0.7449};
+
+    #[test]
+    fn periodicity_check_stops_attracting_cycles_early() {
+        let mut config = RenderConfig::new();
+        config.max_iterations = 100_000;
+        let desc = ThreadDescryptor::from_config(&config);
+        assert!(!in_main_cardioid_or_bulb(&RABBIT));
+        let orbit = iterate(&desc, &RABBIT);
+        assert!(!orbit.escaped && orbit.iterations == config.max_iterations);
+        assert!(orbit.computed < 1000, "{} iterations computed", orbit.computed);
+    }
+
+    #[test]
+    fn periodicity_check_keeps_the_boundary() {
+        let mut config = RenderConfig::new();
+        config.width = 48;
+        config.height = 32;
+        config.center = Vec2::<Real>{x: -0.7453, y: 0.1127};
+        config.set_zoom(0.01);
+        config.max_iterations = 2000;
+        let desc = ThreadDescryptor::from_config(&config);
+        let mut caught = 0;
+        for y in 0..config.height {
+            for x in 0..config.width {
+                let c = pixel_position(&desc, x, y, Vec2::<Real>::new());
+                let orbit = iterate(&desc, &c);
+                if orbit.computed < orbit.iterations && !in_main_cardioid_or_bulb(&c) {
+                    caught += 1;
+                }
+                // Every iteration without the check, the rounding of each step is the same
+                let (mut z, mut iterations) = (Complex {r: 0.0, i: 0.0}, 0);
+                while z.length_squared() <= MAX_LENGTH*MAX_LENGTH && iterations < config.max_iterations {
+                    z = desc.fractal.step(&z, &c, 2);
+                    iterations += 1;
+                }
+                let escaped = z.length_squared() > MAX_LENGTH*MAX_LENGTH;
+                assert_eq!((orbit.escaped, orbit.iterations), (escaped, iterations), "{},{}", x, y);
+                if escaped {
+                    assert_eq!(orbit.z, z);
+                }
+            }
+        }
+        assert!(caught > 0);
+    }
+
+    #[test]
+    fn palette_interpolates_between_stops() {
+        let palette = Palette::new(vec![Color::rgb(0.0, 0.0, 0.0), Color::rgb(1.0, 0.5, 0.0), Color::rgb(1.0, 1.0, 1.0)]);
+        assert_eq!(palette.sample(0.0), palette[0]);
+        assert_eq!(palette.sample(0.5), palette[1]);
+        assert_eq!(palette.sample(1.0), palette[2]);
+        assert_eq!(palette.sample(0.25), Color::rgb(0.5, 0.25, 0.0));
+        assert_eq!(palette.sample(-1.0), palette[0]);
+        assert_eq!(palette.sample(2.0), palette[2]);
+
+        assert_eq!(palette.cycle(1.0), palette[1]);
+        assert_eq!(palette.cycle(2.5), Color::rgb(0.5, 0.5, 0.5));
+        assert_eq!(palette.cycle(4.0), palette.cycle(1.0));
+        assert_eq!(palette.cycle(-2.0), palette[1]);
+        assert_eq!(Palette::new(vec![Color::rgb(0.2, 0.4, 0.6)]).sample(0.7), Color::rgb(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn hsv_palette_sweeps_the_primaries() {
+        let palette = hsv_palette(0.0, 1.0, 1.0, 3);
+        let expected = [Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 1.0, 0.0), Color::rgb(0.0, 0.0, 1.0)];
+        assert_eq!(palette.len(), 3);
+        for (color, expected) in palette.iter().zip(&expected) {
+            for (channel, expected) in [color.r, color.g, color.b].iter().zip(&[expected.r, expected.g, expected.b]) {
+                assert!((channel - expected).abs() < 1e-5);
+            }
+        }
+        assert_eq!(Color::from_hsv(120.0, 0.0, 0.5), Color::rgb(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn seeded_render_is_reproducible() {
+        let mut config = RenderConfig::new();
+        config.width = 200;
+        config.height = 150;
+        config.sample_count = 2;
+        config.seed = Some(42);
+        config.thread_count = 1;
+        let first = render_mandelbrot(&config);
+        config.thread_count = 3;
+        let second = render_mandelbrot(&config);
+        assert!(first == second);
+    }
+
+    #[test]
+    fn inside_color_ignores_the_iteration_cap() {
+        let mut config = RenderConfig::new();
+        config.width = 8;
+        config.height = 8;
+        config.center = Vec2::<Real>{x: -0.1, y: 0.0};
+        config.set_zoom(0.01);
+        config.sample_count = 1;
+        config.gamma = false;
+        config.inside_color = Color::rgb(0.2, 0.6, 0.3);
+        for max_iterations in [250, 251] {
+            config.max_iterations = max_iterations;
+            assert!(render_mandelbrot(&config).iter().all(|&color| color == config.inside_color));
+        }
+    }
+
+    #[test]
+    fn auto_iterations_grow_with_the_zoom() {
+        let mut config = RenderConfig::new();
+        config.width = 100;
+        config.height = 100;
+        for (zoom, max_iterations) in [(4.0, 100), (1.0, 100), (1e-3, 550), (1e-6, 1000)] {
+            config.set_zoom(zoom);
+            config.set_auto_iterations(100.0, 150.0);
+            assert_eq!(config.max_iterations, max_iterations, "zoom {}", zoom);
+        }
+    }
+
+    #[test]
+    fn imaginary_axis_points_up_unless_flipped() {
+        // The view spans [-0.5:1.5] of the imaginary axis, the real axis is inside the set at row 30
+        // and the point 1 above it is far outside
+        let mut config = RenderConfig::new();
+        config.width = 40;
+        config.height = 40;
+        config.center = Vec2::<Real>{x: -0.5, y: 0.5};
+        config.set_zoom(2.0);
+        config.sample_count = 1;
+        config.gamma = false;
+        for flip_y in [false, true] {
+            config.flip_y = flip_y;
+            let (axis, above) = match flip_y {
+                false => (30 * 40 + 20, 10 * 40 + 20),
+                true => (9 * 40 + 20, 29 * 40 + 20),
+            };
+            let iterations = render_iterations(&config);
+            assert!(iterations[axis] < 0.0 && iterations[above] >= 0.0, "flip_y {}", flip_y);
+            for deep_zoom in [false, true] {
+                config.deep_zoom = deep_zoom;
+                let colors = render_mandelbrot(&config);
+                assert!(colors[axis] == INTERIOR_COLOR && colors[above] != INTERIOR_COLOR, "flip_y {} deep_zoom {}", flip_y, deep_zoom);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_palettes_are_blended_as_they_are() {
+        let mut config = RenderConfig::new();
+        config.width = 4;
+        config.height = 4;
+        config.center = Vec2::<Real>{x: 3.0, y: 3.0};
+        config.sample_count = 1;
+        config.palette = Palette::new(vec![Color::rgb(0.5, 0.5, 0.5)]);
+        assert!(render_mandelbrot(&config).iter().all(|color| (color.r - srgb_to_linear(0.5)).abs() < 1e-6));
+        config.color_space = ColorSpace::Linear;
+        assert!(render_mandelbrot(&config).iter().all(|color| color.r == 0.5));
+    }
+
+    #[test]
+    fn center_sampling_is_deterministic_without_a_seed() {
+        let mut config = RenderConfig::new();
+        config.width = 24;
+        config.height = 16;
+        config.sample_count = 1;
+        config.sampling = SamplingMode::Center;
+        config.coloring = ColoringMode::Smooth;
+        let colors = render_mandelbrot(&config);
+        assert_eq!(colors, render_mandelbrot(&config));
+        // Every pixel is colored from the orbit of its center
+        let palette = config.palette.to_linear();
+        let centers = map_pixel_centers(&config, |desc, orbit| sample_color(desc, &palette, &[], orbit));
+        assert_eq!(colors, centers);
+    }
+
+    #[test]
+    fn interior_distance_bounds_the_distance_to_the_boundary() {
+        let mut desc = ThreadDescryptor::new();
+        desc.interior = InteriorColoring::Distance;
+        desc.max_iterations = 1000;
+        // The centers of the main cardioid and the period 2 bulb are 1/4 away from the boundary,
+        // the estimate is at least that and at most 4 times it
+        for c in [Complex {r: 0.0, i: 0.0}, Complex {r: -1.0, i: 0.0}] {
+            let distance = iterate(&desc, &c).interior_distance.unwrap();
+            assert!((0.25..=1.0).contains(&distance), "{} at {:?}", distance, c);
+        }
+        // Close to the boundary the estimate shrinks with the distance
+        let near = iterate(&desc, &Complex {r: 0.24, i: 0.0}).interior_distance.unwrap();
+        assert!(near > 0.0 && near <= 0.04, "{}", near);
+        assert!(iterate(&desc, &Complex {r: 0.3, i: 0.0}).interior_distance.is_none());
+    }
+
+    #[test]
+    fn palette_offset_wraps_around_the_palette() {
+        let mut config = RenderConfig::new();
+        config.width = 16;
+        config.height = 12;
+        config.sample_count = 1;
+        config.seed = Some(1);
+        config.coloring = ColoringMode::Smooth;
+        let unshifted = render_mandelbrot(&config);
+        config.palette_offset = config.palette.len() as Real;
+        let wrapped = render_mandelbrot(&config);
+        for (a, b) in unshifted.iter().zip(&wrapped) {
+            assert!((a.r - b.r).abs() < 1e-3 && (a.g - b.g).abs() < 1e-3 && (a.b - b.b).abs() < 1e-3);
+        }
+        config.palette_offset = 0.5;
+        assert!(render_mandelbrot(&config) != unshifted);
+    }
+
+    #[test]
+    fn adaptive_sampling_only_refines_varying_pixels() {
+        let mut config = RenderConfig::new();
+        config.width = 16;
+        config.height = 16;
+        config.center = Vec2::<Real>{x: -0.1, y: 0.0};
+        config.set_zoom(0.01);
+        config.sample_count = 16;
+        config.adaptive_threshold = Some(0.001);
+        let (_, stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        assert_eq!(stats.samples, (stats.pixels * ADAPTIVE_SAMPLES) as u64);
+
+        // Pixels on the boundary take all of them
+        config.center = Vec2::<Real>{x: -0.75, y: 0.1};
+        let (_, stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        assert!(stats.samples > (stats.pixels * ADAPTIVE_SAMPLES) as u64);
+        assert!(stats.samples <= (stats.pixels * config.sample_count) as u64);
+    }
+
+    #[test]
+    fn detail_sampling_follows_the_neighbors() {
+        let mut config = RenderConfig::new();
+        config.width = 16;
+        config.height = 16;
+        config.center = Vec2::<Real>{x: -0.1, y: 0.0};
+        config.set_zoom(0.01);
+        config.sample_count = 16;
+        config.detail_threshold = Some(0.5);
+        let (_, stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        assert_eq!(stats.samples, stats.pixels as u64);
+
+        // The samples of every pixel match its first pass, some boundary pixels take all of them
+        config.center = Vec2::<Real>{x: -0.75, y: 0.1};
+        let samples = detail_samples(&config, 0.5);
+        assert!(samples.contains(&config.sample_count) && samples.contains(&1));
+        let (_, stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        assert_eq!(stats.samples, samples.iter().sum::<usize>() as u64);
+    }
+
+    #[test]
+    fn fast_fill_skips_flat_rectangles() {
+        let mut config = RenderConfig::new();
+        config.width = 96;
+        config.height = 64;
+        config.center = Vec2::<Real>{x: -0.2, y: 0.0};
+        config.set_zoom(1.2);
+        config.sample_count = 1;
+        config.seed = Some(86);
+        let (colors, stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        config.fast_fill = true;
+        let (filled, fill_stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        assert_eq!(fill_stats.pixels, stats.pixels);
+        assert!(fill_stats.samples * 3 < stats.samples * 2, "{} of {} samples", fill_stats.samples, stats.samples);
+        // The jitter of the samples differs, so only the interior matches exactly
+        let inside = colors.iter().zip(&filled).filter(|(color, fill)| **color == INTERIOR_COLOR && **fill == INTERIOR_COLOR).count();
+        assert!(inside * 10 >= colors.iter().filter(|color| **color == INTERIOR_COLOR).count() * 9);
+        assert!(filled.iter().all(|color| color.a != 0.0));
+    }
+
+    #[test]
+    fn fast_fill_matches_the_anti_aliased_border() {
+        let mut config = RenderConfig::new();
+        config.width = 96;
+        config.height = 64;
+        config.center = Vec2::<Real>{x: -0.75, y: 0.1};
+        config.set_zoom(0.4);
+        config.sample_count = 9;
+        config.seed = Some(86);
+        let (colors, stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        config.fast_fill = true;
+        let (filled, fill_stats) = render_mandelbrot_with_stats(&config, &AtomicBool::new(false));
+        assert!(fill_stats.samples < stats.samples, "{} of {} samples", fill_stats.samples, stats.samples);
+        // Only the anti-aliased pixels between bands differ, their jitter is drawn in another order
+        let close = |a: &Color, b: &Color| (a.r - b.r).abs() + (a.g - b.g).abs() + (a.b - b.b).abs() < 1e-4;
+        let matching = colors.iter().zip(&filled).filter(|(color, fill)| close(color, fill)).count();
+        assert!(matching * 100 >= colors.len() * 85, "{} of {} pixels match", matching, colors.len());
+
+        // Inside a border of one band with differing colors, the fill is the mean of the border
+        let mut desc = ThreadDescryptor::new();
+        desc.thread_size = Vec2::<usize>{x: 8, y: 8};
+        let mut buffer = vec![Color::new(); 64];
+        let shade = |x: usize, y: usize| (Color::rgb(x as Real, y as Real, 1.0), Work {pixels: 1, ..Work::default()}, Some((7, true)));
+        {
+            let mut rows: Vec<&mut [Color]> = buffer.chunks_mut(8).collect();
+            fill_rectangles(&mut rows, &desc, &AtomicUsize::new(0), shade);
+        }
+        assert_eq!(buffer[3*8 + 4], Color::rgb(3.5, 3.5, 1.0));
+        assert_eq!(buffer[7*8 + 2], Color::rgb(2.0, 7.0, 1.0));
+        // A border pixel whose samples fall in two bands splits the rectangle
+        let mut buffer = vec![Color::new(); 64];
+        let mixed = |x: usize, y: usize| (Color::rgb(x as Real, y as Real, 1.0), Work::default(), if (x, y) == (0, 0) { None } else { Some((7, true)) });
+        {
+            let mut rows: Vec<&mut [Color]> = buffer.chunks_mut(8).collect();
+            fill_rectangles(&mut rows, &desc, &AtomicUsize::new(0), mixed);
+        }
+        assert_ne!(buffer[3*8 + 4], Color::rgb(3.5, 3.5, 1.0));
+
+        // Colorings that vary inside a band can't be filled
+        for coloring in [ColoringMode::Smooth, ColoringMode::Distance, ColoringMode::Slope, ColoringMode::OrbitTrap {trap: OrbitTrap::Horizontal {im: 0.0}}] {
+            config.coloring = coloring;
+            assert!(config.validate().is_err(), "{:?}", coloring);
+        }
+        config.coloring = ColoringMode::Banded;
+        config.interior = InteriorColoring::Magnitude;
+        assert!(config.validate().is_err());
+        config.interior = InteriorColoring::Flat;
+        config.fractal = FractalKind::Newton;
+        assert!(config.validate().is_err());
+        config.fractal = FractalKind::Mandelbrot;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn debug_tiles_color_each_tile_apart() {
+        let mut config = RenderConfig::new();
+        config.width = 70;
+        config.height = 50;
+        config.tile_size = 16;
+        config.debug_tiles = true;
+        let colors = render_mandelbrot(&config);
+        // Every pixel matches its right and lower neighbors exactly when they share a tile
+        for y in 0..config.height {
+            for x in 0..config.width {
+                let color = colors[y*config.width + x];
+                assert!(color.a != 0.0, "{},{} is uncovered", x, y);
+                if x + 1 < config.width {
+                    assert_eq!(color == colors[y*config.width + x + 1], (x + 1) % 16 != 0, "{},{}", x, y);
+                }
+                if y + 1 < config.height {
+                    assert_eq!(color == colors[(y + 1)*config.width + x], (y + 1) % 16 != 0, "{},{}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tile_orders_start_from_the_center() {
+        let mut config = RenderConfig::new();
+        config.width = 5 * 16;
+        config.height = 3 * 16;
+        config.tile_size = 16;
+        let descryptors = tile_descryptors(&config);
+        assert_eq!(tile_queue_order(&config, &descryptors), (0..15).collect::<Vec<usize>>());
+        // The middle tile of the 5x3 grid, then the ring of 8 around it clockwise from the right
+        config.tile_order = TileOrder::Spiral;
+        assert_eq!(tile_queue_order(&config, &descryptors)[..9], [7, 8, 13, 12, 11, 6, 1, 2, 3]);
+        config.tile_order = TileOrder::CenterOut;
+        let order = tile_queue_order(&config, &descryptors);
+        assert_eq!(order[..5], [7, 2, 6, 8, 12]);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..15).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn render_covers_every_pixel() {
+        let mut config = RenderConfig::new();
+        config.width = 300;
+        config.height = 260;
+        config.view_size = Vec2::<Real>{x: 3.0, y: 2.6};
+        config.center = Vec2::<Real>{x: -0.5, y: 0.0};
+        config.sample_count = 1;
+        config.thread_count = 2;
+
+        let color_buffer = render_mandelbrot(&config);
+        assert_eq!(color_buffer.len(), config.width * config.height);
+        for (i, color) in color_buffer.iter().enumerate() {
+            // Every palette entry is opaque while Color::new() is fully transparent
+            assert!(color.a != 0.0, "pixel ({}, {}) was not rendered", i % config.width, i / config.width);
+        }
+    }
+
+    // Asserts that the tiles of config are non-empty and cover its output region exactly once
+    fn assert_tiles_cover(config: &RenderConfig) {
+        let tiles = tile_descryptors(config);
+        assert_eq!(tiles.len(), tile_count(config));
+
+        let region = config.output_region();
+        let mut covered = vec![0; config.width * config.height];
+        for tile in &tiles {
+            assert!(tile.thread_size.x > 0 && tile.thread_size.y > 0);
+            for y in tile.offset.y..tile.offset.y + tile.thread_size.y {
+                for x in tile.offset.x..tile.offset.x + tile.thread_size.x {
+                    covered[y * config.width + x] += 1;
+                }
+            }
+        }
+        for y in 0..config.height {
+            for x in 0..config.width {
+                let inside = (region.x..region.x + region.width).contains(&x) && (region.y..region.y + region.height).contains(&y);
+                assert_eq!(covered[y * config.width + x], inside as i32, "{}x{} with {} pixel tiles and region {:?}",
+                    config.width, config.height, config.tile_size, config.region);
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_cover_the_image_exactly_once() {
+        let sizes = [(1366, 768, 128), (300, 260, 64), (64, 64, 64), (65, 1, 64), (7, 13, 3), (1, 1, 200)];
+        for &(width, height, tile_size) in &sizes {
+            let mut config = RenderConfig::new();
+            config.width = width;
+            config.height = height;
+            config.tile_size = tile_size;
+            assert_tiles_cover(&config);
+        }
+
+        let mut config = RenderConfig::new();
+        config.tile_size = 128;
+        let last = tile_descryptors(&config).pop().unwrap();
+        assert_eq!((last.thread_size.x, last.thread_size.y), (1366 - 10*128, 768 - 5*128));
+    }
+
+    #[test]
+    fn tiles_cover_random_small_images() {
+        let mut rng = StdRng::seed_from_u64(79);
+        for _ in 0..500 {
+            let mut config = RenderConfig::new();
+            config.width = rng.gen_range(1, 40);
+            config.height = rng.gen_range(1, 40);
+            config.tile_size = rng.gen_range(1, 48);
+            if rng.gen() {
+                let (x, y) = (rng.gen_range(0, config.width), rng.gen_range(0, config.height));
+                let (width, height) = (rng.gen_range(1, config.width - x + 1), rng.gen_range(1, config.height - y + 1));
+                config.region = Some(Region {x, y, width, height});
+            }
+            assert!(config.validate().is_ok());
+            assert_tiles_cover(&config);
+        }
+    }
+
+    #[test]
+    fn bounds_are_checked_and_widened_to_the_aspect() {
+        let mut config = RenderConfig::new();
+        config.width = 200;
+        config.height = 100;
+        // Inverted, zero area and NaN rectangles are refused before the view changes
+        for (x_min, x_max, y_min, y_max) in [(1.0, -1.0, -1.0, 1.0), (-1.0, 1.0, 1.0, -1.0), (-1.0, 1.0, 0.5, 0.5), (0.0, 0.0, -1.0, 1.0), (Real::NAN, 1.0, -1.0, 1.0), (-1.0, 1.0, -1.0, Real::NAN)] {
+            assert!(config.set_bounds(x_min, x_max, y_min, y_max).is_err(), "{},{},{},{}", x_min, x_max, y_min, y_max);
+            assert!(config.validate().is_ok());
+        }
+        // Infinite bounds order fine but leave no finite view to render
+        for (x_min, x_max, y_min, y_max) in [(Real::NEG_INFINITY, 1.0, -1.0, 1.0), (-1.0, 1.0, -1.0, Real::INFINITY), (Real::NEG_INFINITY, Real::INFINITY, -1.0, 1.0)] {
+            let mut infinite = config.clone();
+            infinite.set_bounds(x_min, x_max, y_min, y_max).unwrap();
+            assert!(infinite.validate().is_err(), "{},{},{},{}", x_min, x_max, y_min, y_max);
+        }
+        for view_size in [Vec2::<Real>{x: 0.0, y: 1.0}, Vec2::<Real>{x: 1.0, y: -1.0}] {
+            let mut empty = config.clone();
+            empty.view_size = view_size;
+            assert!(empty.validate().is_err());
+        }
+
+        // A square view on a 2:1 image grows horizontally around its center
+        config.set_bounds(-1.5, 0.5, -1.0, 1.0).unwrap();
+        assert_eq!(config.pixel_aspect(), 0.5);
+        config.preserve_aspect();
+        assert_eq!((config.center.x, config.center.y), (-0.5, 0.0));
+        assert_eq!((config.view_size.x, config.view_size.y), (4.0, 2.0));
+        assert_eq!(config.pixel_aspect(), 1.0);
+        // A wide view grows vertically instead
+        config.set_bounds(-2.0, 2.0, 0.0, 1.0).unwrap();
+        config.preserve_aspect();
+        assert_eq!((config.center.x, config.center.y), (0.0, 0.5));
+        assert_eq!((config.view_size.x, config.view_size.y), (4.0, 2.0));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn region_matches_the_full_render() {
+        let mut config = RenderConfig::new();
+        config.width = 200;
+        config.height = 150;
+        config.sample_count = 2;
+        config.seed = Some(7);
+        config.tile_size = 32;
+        let full = render_mandelbrot(&config);
+
+        // Starts on a tile corner and ends at the image corner, so every tile is the same
+        config.region = Some(Region {x: 64, y: 32, width: 136, height: 118});
+        let region = render_mandelbrot(&config);
+        assert_eq!(region.len(), 136 * 118);
+        for (y, row) in region.chunks(136).enumerate() {
+            assert!(row == &full[(32 + y) * 200 + 64..(33 + y) * 200]);
+        }
+    }
+}
@@ -1,306 +1,963 @@
+mod config_file;
+mod interactive;
+
+use std::env;
+use std::fs;
 use std::path::Path;
-use std::fs::File;
-use std::io::{BufWriter, stdout, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::str::FromStr;
 use std::time;
-use std::sync::{Mutex, Arc, mpsc::channel, mpsc::Sender};
-use rand::Rng;
-use std::thread;
+use mandelbrot_set::{Apng, BitDepth, STDOUT_PATH, Checkpoint, Color, ColorScale, ColorSpace, ColoringMode, Complex, EdgeStyle, InteriorColoring, Palette, downsample, draw_grid, FractalKind, OrbitTrap, PRESETS, RenderConfig, Real, Region, RenderStats, ResampleFilter, SamplingMode, SaveOptions, TileOrder, ToneMap, Vec2, Keyframe, find_preset, hsv_palette, interpolate_keyframes, interpolate_zoom, load_keyframes, load_palette, render_iterations, render_mandelbrot_interruptible, render_mandelbrot_resumable, render_mandelbrot_with_stats, render_edges, save_image_auto, save_iterations, OutputSettings, animation_metadata, render_metadata, save_metadata};
 
-type Real = f32;
+// Set by the Ctrl-C handler, the render stops starting new tiles and the partial image is saved
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
-#[derive(Copy, Clone)]
-struct Complex {
-    r: Real,
-    i: Real,
+#[cfg(unix)]
+extern "C" fn handle_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    // A second Ctrl-C kills the process right away
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
 }
 
-impl Complex {
-    fn squared(&self) -> Complex {
-        Complex {r: self.r*self.r - self.i*self.i, i: 2.0*self.r*self.i}
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    unsafe {
+        libc::signal(libc::SIGINT, handle_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
     }
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+// Set by --quiet, silences the status messages and the progress bar
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// Prints a status message to stderr unless --quiet was given, stdout is kept for the image data
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+const USAGE: &str = "Usage: mandelbrot_set [OPTIONS]
+
+Options:
+    --config <file>                 Read options from a TOML file of top level keys named like the flags, e.g. max_iterations = 500, later flags override them
+    --width <pixels>                Image width (default 1366)
+    --height <pixels>               Image height (default 768)
+    --center <x>,<y>                Center of the view in the complex plane, every digit is kept for --precision (default -0.7453,0.1127)
+    --zoom <size>                   Height of the view in the complex plane, the width follows the aspect ratio (default 6.5E-4)
+    --bounds <x0>,<x1>,<y0>,<y1>    View rectangle in the complex plane, replaces --center and --zoom
+    --preserve-aspect               Grow the --bounds view around its center to the image aspect instead of stretching the fractal
+    --flip-y                        Point the imaginary axis down the image instead of up, mirroring it top to bottom
+    --region <x>,<y>,<w>,<h>        Only render the w*h pixels at x,y of the image and save them as a w*h image, for stitching renders together
+    --preset <name>                 Start from a named location, see --list-presets
+    --list-presets                  Print the named locations
+    --quality <level>               Samples and iterations preset: draft (4, 100), normal (16, 250) or high (64, 1000), overridden by --samples and --max-iterations
+    --samples <count>               Samples per pixel (default 16)
+    --samples-adaptive <variance>   Start with 4 samples per pixel and only take all --samples where their color variance is above this, e.g. 0.002
+    --samples-detail <gradient>     Take 1 sample per pixel in flat areas and up to all --samples where the escape level of neighboring pixels differs by this, e.g. 0.5
+    --sampling <mode>               Sample placement: stratified over a k*k grid or random (default stratified)
+    --antialias <mode>              jitter spreads --samples over every pixel, none takes one sample at its center without randomness for fast deterministic drafts (default jitter)
+    --fast-fill                     Skip rectangles whose border escapes at a single iteration and fill them with its color, faster but can miss thin filaments, banded and histogram coloring only
+    --debug-tiles                   Fill every tile with a solid color from its index instead of the fractal, to check the tiles cover the image
+    --mode <fractal>                Fractal to render: mandelbrot, burning-ship, tricorn, buddhabrot, newton for z^3 - 1 colored by root, nova or multibrot-julia (default mandelbrot)
+    --julia <re>,<im>               Render the Julia set of the given constant instead of the Mandelbrot set, of any --power
+    --power <d>                     Exponent of the recurrence z = z^d + c, or of z^d - 1 for nova (default 2)
+    --relax <r>                     Relaxation factor of the Newton step of --mode nova (default 1)
+    --deep-zoom                     Iterate the Mandelbrot set by perturbation around an f64 reference orbit per tile, for zooms below about 1e-5 in f32 or 1e-13 in f64
+    --precision <bits>              Iterate in fixed point numbers with this many fractional bits, exact at any zoom but far slower than --deep-zoom
+    --max-iterations <n>            Iterations after which a point is considered inside the set, auto picks them from the view width (default 250)
+    --auto-iterations <b>,<k>       Iterations of --max-iterations auto: b plus k for every decade the view is narrower than 1 (default 100,150)
+    --bailout <radius>              Escape radius, larger values give smoother coloring (default 2)
+    --buddhabrot-min <n>            Buddhabrot orbits escaping in fewer iterations are discarded (default 0)
+    --buddhabrot-max <r>,<g>,<b>    Buddhabrot iteration limit of every exposure (default 5000,500,50)
+    --coloring <mode>               Palette mapping: banded, smooth, distance, slope, histogram or trap (default banded)
+    --light-angle <degrees>         Light direction of --coloring slope, counterclockwise from the real axis (default 45)
+    --light-height <h>              Light height above the plane of --coloring slope, higher is flatter (default 1.5)
+    --inside-color <r>,<g>,<b>      Color of the points inside the set in [0:1], unless --interior-color or the coloring colors them (default 0,0,0)
+    --palette-repeat <factor>       Palette entries per iteration of the banded and smooth colorings, below 1 gives fewer and wider bands (default 1)
+    --palette-offset <entries>      Palette entries to rotate the palette by, fractions blend between entries in smooth colorings (default 0)
+    --color-scale <scale>           Iteration count transform before the palette lookup: linear, sqrt or log (default linear)
+    --interior-color <mode>         Color of the points inside the set from the last z of their orbit: flat, magnitude, angle or distance (default flat)
+    --interior-distance             Same as --interior-color distance, a glow fading from the boundary into the bulbs by their interior distance estimate
+    --trap <shape>                  Orbit trap of --coloring trap: point:<re>,<im>, horizontal:<im> or vertical:<re> (default point:0,0)
+    --palette <path>                Load the palette from a text file of \"r g b\" lines or a JSON array of [r, g, b] (default built-in)
+    --palette-hsv <h>,<s>,<v>,<n>   Generate a palette of n colors sweeping the hue wheel from hue h in degrees, at saturation s and value v in [0:1]
+    --seed <u64>                    Seed the sampling jitter so renders are reproducible (default random)
+    --threads <count>               Worker threads, 0 uses every available core (default 0)
+    --supersample <factor>          Render at factor times the resolution and shrink the image down (default 1)
+    --filter <name>                 Filter used to shrink a supersampled image: box or lanczos (default box)
+    --tile-size <pixels>            Side of the square tiles the workers pull from the queue (default 64)
+    --tile-order <order>            Order the tiles are rendered in: row (default), spiral or center-out, which fill the middle of the image first
+    --frames <count>                Render a zoom animation of count frames named frame_0001, frame_0002... next to --out
+    --zoom-start <size>             View height of the first animation frame (default 3)
+    --zoom-end <size>               View height of the last animation frame (default --zoom)
+    --keyframes <path>              Fly the --frames animation through the views of a JSON keyframe file instead of zooming, see the README
+    --animate <path>                Save the --frames animation as a looping animated PNG instead of numbered frames
+    --frame-delay <ms>              Display time of every animated PNG frame (default 40)
+    --depth <bits>                  Bits per channel of the saved image, 8 or 16 (default 8)
+    --iterations-out <path>         Also save the continuous iteration count of every pixel as a PFM float image, -1 inside the set
+    --grayscale                     Save a single channel gray ramp of the iteration count, white escapes first and the set is black
+    --edges                         Save a line drawing of where the escape time changes sharply instead of the colored fractal, e.g. for plotters
+    --edge-threshold <g>            Gradient of ln(1 + iterations) drawn as a full line by --edges, lower draws more lines (default 1)
+    --edge-colors <line>,<back>     Line and background colors of --edges as r,g,b,r,g,b in [0:1] (default 0,0,0,1,1,1)
+    --dither                        Add an ordered dither before quantizing the saved channels, hides banding of slow gradients
+    --exposure <stops>              Brighten the linear colors by this many stops before saving them, negative values darken (default 0)
+    --tone-map <curve>              Map the exposed colors into the saved range: none clips them, reinhard or aces roll highlights off (default none)
+    --color-space <space>           Encoding of the palette, inside and edge colors: srgb display values are decoded to linear light before blending, linear ones are blended as they are (default srgb)
+    --no-gamma                      Blend and save the palette colors as they are instead of in linear light
+    --resume <path>                 Continue the render saved in a checkpoint, renders are checkpointed to <out>.checkpoint
+    --out <path>                    Output image path, .ppm writes a binary PPM, .bmp a 24-bit BMP and anything else a PNG, - writes a PNG to stdout (default output/image.png)
+    --no-metadata                   Don't save the render settings as <out name>.json next to every image
+    --grid                          Draw lines at round coordinates over the image with brighter axes and labels, for teaching figures
+    --preview                       First save a quick 256 pixel wide render with one sample as preview.png next to --out
+    --interactive                   Explore the view in a terminal preview, the r command renders it to --out
+    --benchmark                     Render a fixed view without saving it and print the pixels and iterations per second, only --threads applies
+    --verbose                       Print the render time, pixels and iterations of every tile as it finishes, to find the tiles that hold up a render
+    --quiet                         Don't print the progress bar and status messages, errors are still printed
+    --help                          Print this message";
+
+// Relative pixel aspect difference past which --bounds warns about stretching
+const ASPECT_TOLERANCE: Real = 0.01;
+
+// Base and iterations per decade of zoom of --max-iterations auto
+const AUTO_ITERATIONS: (Real, Real) = (100.0, 150.0);
+
+// Zoom sequence towards the configured center
+struct Animation {
+    frames: usize,
+    zoom_start: Real,
+    zoom_end: Real,
+    // Writes a single animated PNG there instead of numbered frames
+    apng: Option<String>,
+    // Display time of every APNG frame in milliseconds
+    delay: u16,
+    // Flies through these views instead of zooming from zoom_start to zoom_end
+    keyframes: Option<Vec<Keyframe>>,
+    // Base and per decade iterations of --max-iterations auto, every frame gets its own
+    auto_iterations: Option<(Real, Real)>,
+}
 
-    fn add(&self, rhs: &Complex) -> Complex {
-        Complex {r: self.r + rhs.r, i: self.i + rhs.i}
+struct Options {
+    config: RenderConfig,
+    out: String,
+    save: SaveOptions,
+    animation: Option<Animation>,
+    interactive: bool,
+    iterations_out: Option<String>,
+    // Supersampling factor and the filter the rendered buffer is shrunk with
+    supersample: usize,
+    filter: ResampleFilter,
+    resume: Option<String>,
+    // Renders the edge map instead of the colored fractal
+    edges: Option<EdgeStyle>,
+    // Saves a quick low resolution render before the full one
+    preview: bool,
+    // Writes a JSON sidecar next to every saved image
+    metadata: bool,
+    // Draws the coordinate grid over every image
+    grid: bool,
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    value.parse::<T>().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+// Parses a comma separated list of exactly count values
+fn parse_list<T: FromStr>(flag: &str, value: Option<&String>, count: usize) -> Result<Vec<T>, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    let parts: Vec<&str> = value.split(',').collect();
+    if parts.len() != count {
+        return Err(format!("invalid value for {}: {} (expected {} comma separated values)", flag, value, count));
     }
+    parts.iter().map(|part| part.trim().parse::<T>().map_err(|_| format!("invalid value for {}: {}", flag, value))).collect()
+}
 
-    fn length(&self) -> Real {
-        (self.r*self.r + self.i*self.i).sqrt()
+// Parses "<x>,<y>" into a pair of reals
+fn parse_pair(flag: &str, value: Option<&String>) -> Result<Vec2<Real>, String> {
+    let values = parse_list::<Real>(flag, value, 2)?;
+    Ok(Vec2::<Real>{x: values[0], y: values[1]})
+}
+
+// Parses "<x>,<y>" into the center of config, keeping every digit for --precision
+fn parse_center(flag: &str, value: Option<&String>, config: &mut RenderConfig) -> Result<(), String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    if parts.len() != 2 {
+        return Err(format!("invalid value for {}: {} (expected 2 comma separated values)", flag, value));
     }
+    config.set_center_digits(parts[0], parts[1]).map_err(|e| format!("invalid value for {}: {}", flag, e))
 }
 
-#[derive(Copy, Clone)]
-struct Vec2<T> {
-    x: T,
-    y: T,
+// Parses "<h>,<s>,<v>,<steps>" into a palette sweeping the hue wheel
+fn parse_hsv_palette(flag: &str, value: Option<&String>) -> Result<Palette, String> {
+    let values = parse_list::<Real>(flag, value, 4)?;
+    let (saturation, brightness, steps) = (values[1], values[2], values[3]);
+    if !(0.0..=1.0).contains(&saturation) || !(0.0..=1.0).contains(&brightness) || steps < 1.0 || steps.fract() != 0.0 {
+        return Err(format!("invalid value for {}: {} (saturation and value in [0:1], steps a positive integer)", flag, value.unwrap()));
+    }
+    Ok(hsv_palette(values[0], saturation, brightness, steps as usize))
 }
 
-impl Vec2<usize> {
-    fn new() -> Vec2<usize> {
-        Vec2{x:0, y:0}
+fn parse_mode(flag: &str, value: Option<&String>) -> Result<FractalKind, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "mandelbrot" => Ok(FractalKind::Mandelbrot),
+        "burning-ship" => Ok(FractalKind::BurningShip),
+        "tricorn" => Ok(FractalKind::Tricorn),
+        "buddhabrot" => Ok(FractalKind::Buddhabrot),
+        "newton" => Ok(FractalKind::Newton),
+        "nova" => Ok(FractalKind::Nova {relax: 1.0}),
+        "multibrot-julia" => Ok(FractalKind::Julia {c: Complex {r: 0.0, i: 0.0}}),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
     }
 }
 
-impl Vec2<Real> {
-    fn new() -> Vec2<Real> {
-        Vec2{x:0.0, y:0.0}
+fn parse_coloring(flag: &str, value: Option<&String>) -> Result<ColoringMode, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "banded" => Ok(ColoringMode::Banded),
+        "smooth" => Ok(ColoringMode::Smooth),
+        "distance" => Ok(ColoringMode::Distance),
+        "histogram" => Ok(ColoringMode::Histogram),
+        "slope" => Ok(ColoringMode::Slope),
+        "trap" => Ok(ColoringMode::OrbitTrap {trap: OrbitTrap::Point {center: Complex {r: 0.0, i: 0.0}}}),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
     }
 }
 
-#[derive(Copy, Clone)]
-struct Color {
-    r: Real,
-    g: Real,
-    b: Real,
-    a: Real,
+// Parses point:<re>,<im>, horizontal:<im> or vertical:<re>
+fn parse_trap(flag: &str, value: Option<&String>) -> Result<OrbitTrap, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    let invalid = || format!("invalid value for {}: {}", flag, value);
+    let (shape, position) = value.split_once(':').ok_or_else(invalid)?;
+    let position = position.to_string();
+    match shape {
+        "point" => {
+            let center = parse_pair(flag, Some(&position))?;
+            Ok(OrbitTrap::Point {center: Complex {r: center.x, i: center.y}})
+        }
+        "horizontal" => Ok(OrbitTrap::Horizontal {im: parse_value(flag, Some(&position))?}),
+        "vertical" => Ok(OrbitTrap::Vertical {re: parse_value(flag, Some(&position))?}),
+        _ => Err(invalid()),
+    }
 }
 
-impl Color {
-    fn new() -> Color {
-        Color {r:0.0, g:0.0, b:0.0, a:0.0}
+fn parse_color_scale(flag: &str, value: Option<&String>) -> Result<ColorScale, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "linear" => Ok(ColorScale::Linear),
+        "sqrt" => Ok(ColorScale::Sqrt),
+        "log" => Ok(ColorScale::Log),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
     }
+}
 
-    fn add(&mut self, rhs: Color) {
-        self.r += rhs.r;
-        self.g += rhs.g;
-        self.b += rhs.b;
-        self.a += rhs.a;
+fn parse_interior(flag: &str, value: Option<&String>) -> Result<InteriorColoring, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "flat" => Ok(InteriorColoring::Flat),
+        "magnitude" => Ok(InteriorColoring::Magnitude),
+        "angle" => Ok(InteriorColoring::Angle),
+        "distance" => Ok(InteriorColoring::Distance),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
     }
+}
 
-    fn divide(&mut self, value: Real) {
-        self.r /= value;
-        self.g /= value;
-        self.b /= value;
-        self.a /= value;
+fn parse_sampling(flag: &str, value: Option<&String>) -> Result<SamplingMode, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "random" => Ok(SamplingMode::Random),
+        "stratified" => Ok(SamplingMode::Stratified),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
     }
 }
 
-fn save_image(color_buffer: &[Color], width: usize, height: usize, path: &str) {
-    let path = Path::new(path);
-    let file = File::create(path).unwrap();
-    let ref mut w = BufWriter::new(file);
-    
-    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
-    encoder.set_color(png::ColorType::RGBA);
-    encoder.set_depth(png::BitDepth::Eight);
-    let mut writer = encoder.write_header().unwrap();
-    
-    let mut rgba_data = vec![0u8; 4 * width * height];
-    for y in 0..height {
-        for x in 0..width {
-            rgba_data[4 * (y * width + x) + 0] = (color_buffer[y * width + x].r * 255.0) as u8;
-            rgba_data[4 * (y * width + x) + 1] = (color_buffer[y * width + x].g * 255.0) as u8;
-            rgba_data[4 * (y * width + x) + 2] = (color_buffer[y * width + x].b * 255.0) as u8;
-            rgba_data[4 * (y * width + x) + 3] = (color_buffer[y * width + x].a * 255.0) as u8;
-        }
+fn parse_tile_order(flag: &str, value: Option<&String>) -> Result<TileOrder, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "row" => Ok(TileOrder::Row),
+        "spiral" => Ok(TileOrder::Spiral),
+        "center-out" => Ok(TileOrder::CenterOut),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
+    }
+}
+
+// Whether --antialias jitters the samples over the pixels
+fn parse_antialias(flag: &str, value: Option<&String>) -> Result<bool, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "jitter" => Ok(true),
+        "none" => Ok(false),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
+    }
+}
+
+fn parse_color_space(flag: &str, value: Option<&String>) -> Result<ColorSpace, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "srgb" => Ok(ColorSpace::Srgb),
+        "linear" => Ok(ColorSpace::Linear),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
     }
+}
 
-    writer.write_image_data(&rgba_data).unwrap();
+fn parse_tone_map(flag: &str, value: Option<&String>) -> Result<ToneMap, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "none" => Ok(ToneMap::None),
+        "reinhard" => Ok(ToneMap::Reinhard),
+        "aces" => Ok(ToneMap::Aces),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
+    }
 }
 
-// Prints the progress [0:100] as a bar in the console
-fn print_progress(progress: u32) {
-    let mut progress_bar = String::from("[");
-    for i in 0..50 {
-        if i < progress/2 {
-            progress_bar.push('=');
+fn parse_depth(flag: &str, value: Option<&String>) -> Result<BitDepth, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "8" => Ok(BitDepth::Eight),
+        "16" => Ok(BitDepth::Sixteen),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
+    }
+}
+
+// What the command line asks for
+enum Command {
+    Render(Box<Options>),
+    // Holds the thread count of the benchmark render
+    Benchmark(usize),
+    Help,
+    ListPresets,
+}
+
+fn parse_filter(flag: &str, value: Option<&String>) -> Result<ResampleFilter, String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "box" => Ok(ResampleFilter::Box),
+        "lanczos" => Ok(ResampleFilter::Lanczos3),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
+    }
+}
+
+// Samples per pixel and max iterations of a --quality level
+fn parse_quality(flag: &str, value: Option<&String>) -> Result<(usize, u32), String> {
+    let value = value.ok_or(format!("missing value for {}", flag))?;
+    match value.as_str() {
+        "draft" => Ok((4, 100)),
+        "normal" => Ok((16, 250)),
+        "high" => Ok((64, 1000)),
+        _ => Err(format!("invalid value for {}: {}", flag, value)),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    // The flags of a config file go in front of the others, so the command line overrides them
+    if let Some(index) = args.iter().position(|arg| arg == "--config") {
+        let path = args.get(index + 1).ok_or("missing value for --config")?;
+        let mut expanded = config_file::load_flags(path)?;
+        expanded.extend(args[..index].iter().chain(&args[index + 2..]).cloned());
+        return parse_args(&expanded);
+    }
+
+    let mut config = RenderConfig::new();
+    let mut zoom = config.view_size.y;
+    let mut out = String::from("output/image.png");
+    let mut save = SaveOptions::new();
+    let mut bounds = None;
+    let mut centered = false;
+    let mut frames = None;
+    let mut zoom_start = 3.0;
+    let mut zoom_end = None;
+    let mut interactive = false;
+    let mut trap = None;
+    let mut relax = None;
+    let mut julia = None;
+    let mut iterations_out = None;
+    let mut supersample = 1;
+    let mut filter = ResampleFilter::Box;
+    let mut resume = None;
+    let mut apng = None;
+    let mut delay = 40;
+    let mut keyframes = None;
+    let mut edges = false;
+    let mut preview = false;
+    let mut metadata = true;
+    let mut grid = false;
+    let mut edge_style = EdgeStyle::new();
+    let mut benchmark = false;
+    let mut preserve_aspect = false;
+    let mut auto = false;
+    let mut antialias = true;
+    let mut auto_iterations = None;
+
+    // The quality level goes first so the individual flags override it wherever they are, the last
+    // one wins like any other flag
+    if let Some(index) = args.iter().rposition(|arg| arg == "--quality") {
+        let (samples, max_iterations) = parse_quality(&args[index], args.get(index + 1))?;
+        config.sample_count = samples;
+        config.max_iterations = max_iterations;
+    }
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--width" => config.width = parse_value(flag, args.next())?,
+            "--height" => config.height = parse_value(flag, args.next())?,
+            "--center" => {
+                parse_center(flag, args.next(), &mut config)?;
+                centered = true;
+            }
+            "--zoom" => {
+                zoom = parse_value(flag, args.next())?;
+                centered = true;
+            }
+            "--preset" => {
+                let name: String = parse_value(flag, args.next())?;
+                let preset = find_preset(&name).ok_or(format!("unknown preset: {}, see --list-presets", name))?;
+                config.apply_preset(preset);
+                zoom = preset.zoom;
+                centered = true;
+            }
+            "--list-presets" => return Ok(Command::ListPresets),
+            "--bounds" => bounds = Some(parse_list::<Real>(flag, args.next(), 4)?),
+            "--preserve-aspect" => preserve_aspect = true,
+            "--flip-y" => config.flip_y = true,
+            "--region" => {
+                let values = parse_list::<usize>(flag, args.next(), 4)?;
+                config.region = Some(Region {x: values[0], y: values[1], width: values[2], height: values[3]});
+            }
+            "--quality" => {
+                args.next();
+            }
+            "--samples" => config.sample_count = parse_value(flag, args.next())?,
+            "--samples-adaptive" => config.adaptive_threshold = Some(parse_value(flag, args.next())?),
+            "--samples-detail" => config.detail_threshold = Some(parse_value(flag, args.next())?),
+            "--sampling" => config.sampling = parse_sampling(flag, args.next())?,
+            "--antialias" => antialias = parse_antialias(flag, args.next())?,
+            "--fast-fill" => config.fast_fill = true,
+            "--debug-tiles" => config.debug_tiles = true,
+            "--mode" => config.fractal = parse_mode(flag, args.next())?,
+            "--julia" => {
+                let c = parse_pair(flag, args.next())?;
+                julia = Some(Complex {r: c.x, i: c.y});
+            }
+            "--power" => config.exponent = parse_value(flag, args.next())?,
+            "--deep-zoom" => config.deep_zoom = true,
+            "--precision" => config.precision = Some(parse_value(flag, args.next())?),
+            "--max-iterations" => match args.next() {
+                Some(value) if value == "auto" => auto = true,
+                value => {
+                    config.max_iterations = parse_value(flag, value)?;
+                    auto = false;
+                }
+            },
+            "--auto-iterations" => {
+                let values = parse_list::<Real>(flag, args.next(), 2)?;
+                auto_iterations = Some((values[0], values[1]));
+            }
+            "--bailout" => config.bailout = parse_value(flag, args.next())?,
+            "--buddhabrot-min" => config.buddhabrot.min_iterations = parse_value(flag, args.next())?,
+            "--buddhabrot-max" => {
+                let values = parse_list::<u32>(flag, args.next(), 3)?;
+                config.buddhabrot.max_iterations = [values[0], values[1], values[2]];
+            }
+            "--coloring" => config.coloring = parse_coloring(flag, args.next())?,
+            "--light-angle" => config.light.angle = parse_value(flag, args.next())?,
+            "--light-height" => config.light.height = parse_value(flag, args.next())?,
+            "--palette-repeat" => config.color_density = parse_value(flag, args.next())?,
+            "--palette-offset" => config.palette_offset = parse_value(flag, args.next())?,
+            "--color-scale" => config.color_scale = parse_color_scale(flag, args.next())?,
+            "--interior-color" => config.interior = parse_interior(flag, args.next())?,
+            "--interior-distance" => config.interior = InteriorColoring::Distance,
+            "--trap" => trap = Some(parse_trap(flag, args.next())?),
+            "--relax" => relax = Some(parse_value(flag, args.next())?),
+            "--palette" => {
+                let path: String = parse_value(flag, args.next())?;
+                config.palette = load_palette(&path).map_err(|e| e.to_string())?;
+            }
+            "--palette-hsv" => config.palette = parse_hsv_palette(flag, args.next())?,
+            "--seed" => config.seed = Some(parse_value(flag, args.next())?),
+            "--threads" => config.thread_count = parse_value(flag, args.next())?,
+            "--supersample" => supersample = parse_value(flag, args.next())?,
+            "--filter" => filter = parse_filter(flag, args.next())?,
+            "--tile-size" => config.tile_size = parse_value(flag, args.next())?,
+            "--tile-order" => config.tile_order = parse_tile_order(flag, args.next())?,
+            "--frames" => frames = Some(parse_value(flag, args.next())?),
+            "--zoom-start" => zoom_start = parse_value(flag, args.next())?,
+            "--animate" => apng = Some(parse_value(flag, args.next())?),
+            "--frame-delay" => delay = parse_value(flag, args.next())?,
+            "--zoom-end" => zoom_end = Some(parse_value(flag, args.next())?),
+            "--keyframes" => {
+                let path: String = parse_value(flag, args.next())?;
+                keyframes = Some(load_keyframes(&path).map_err(|e| e.to_string())?);
+            }
+            "--depth" => save.bit_depth = parse_depth(flag, args.next())?,
+            "--iterations-out" => iterations_out = Some(parse_value(flag, args.next())?),
+            "--grayscale" => {
+                // Without gamma the gray levels stay proportional to the iteration count
+                config.coloring = ColoringMode::Grayscale;
+                config.gamma = false;
+                save.gamma = false;
+                save.grayscale = true;
+            }
+            "--dither" => save.dither = true,
+            "--exposure" => {
+                save.exposure = parse_value(flag, args.next())?;
+                if !save.exposure.is_finite() {
+                    return Err(format!("exposure must be finite, got {}", save.exposure));
+                }
+            }
+            "--tone-map" => save.tone_map = parse_tone_map(flag, args.next())?,
+            "--edges" => edges = true,
+            "--edge-threshold" => edge_style.threshold = parse_value(flag, args.next())?,
+            "--inside-color" => {
+                let values = parse_list::<Real>(flag, args.next(), 3)?;
+                config.inside_color = Color::rgb(values[0], values[1], values[2]);
+            }
+            "--edge-colors" => {
+                let values = parse_list::<Real>(flag, args.next(), 6)?;
+                edge_style.line = Color::rgb(values[0], values[1], values[2]);
+                edge_style.background = Color::rgb(values[3], values[4], values[5]);
+            }
+            "--color-space" => config.color_space = parse_color_space(flag, args.next())?,
+            "--no-gamma" => {
+                config.gamma = false;
+                save.gamma = false;
+            }
+            "--resume" => resume = Some(parse_value(flag, args.next())?),
+            "--out" => out = parse_value(flag, args.next())?,
+            "--interactive" => interactive = true,
+            "--preview" => preview = true,
+            "--grid" => grid = true,
+            "--no-metadata" => metadata = false,
+            "--benchmark" => benchmark = true,
+            "--verbose" => config.verbose = true,
+            "--quiet" => {
+                QUIET.store(true, Ordering::Relaxed);
+                config.progress = false;
+            }
+            "--help" => return Ok(Command::Help),
+            _ => return Err(format!("unknown option: {}", flag)),
         }
-        else {
-            progress_bar.push(' ');
+    }
+
+    if benchmark {
+        return Ok(Command::Benchmark(config.thread_count));
+    }
+    match bounds {
+        Some(bounds) => {
+            if centered {
+                return Err(String::from("--bounds can't be combined with --center or --zoom"));
+            }
+            config.set_bounds(bounds[0], bounds[1], bounds[2], bounds[3]).map_err(|e| format!("invalid value for --bounds: {}", e))?;
+            if preserve_aspect {
+                config.preserve_aspect();
+            }
+            else if (config.pixel_aspect() - 1.0).abs() > ASPECT_TOLERANCE {
+                status!("warning: --bounds doesn't match the {}x{} image aspect, the fractal is stretched {:.3} times horizontally, --preserve-aspect grows the view to match instead",
+                    config.width, config.height, 1.0 / config.pixel_aspect());
+            }
+            zoom = config.view_size.y;
         }
+        None => config.set_zoom(zoom),
+    }
+    // A single sample at every pixel center, whatever the sample count
+    if !antialias {
+        config.sampling = SamplingMode::Center;
+        config.sample_count = 1;
     }
-    progress_bar.push_str("]");
-    print!("\r{} {}%  ", progress_bar, progress);
-    stdout().flush().unwrap();
-}
-
-fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
-    if value < min {
-        return min;
-    }
-    else if value > max {
-        return max;
-    }
-    value
-}
-
-fn divide_roundup(numinator: usize, denominator: usize) -> usize {
-    if numinator%denominator == 0 {
-        return numinator/denominator;
-    }
-    numinator/denominator+1
-}
-
-fn min<T: PartialOrd>(a: T, b: T) -> T {
-    if a < b {
-        return a;
-    }
-    b
-}
-
-// Render Parameters
-// COLOR PALLETE SOURCE: https://stackoverflow.com/a/16505538/9218594
-const COLOR_PALETTE: [Color; 16] = [
-    Color {r:0.26, g:0.1,  b:0.06, a:1.0}, 
-    Color {r:0.1,  g:0.03, b:0.1,  a:1.0}, 
-    Color {r:0.3,  g:0.01, b:0.18, a:1.0}, 
-    Color {r:0.02, g:0.02, b:0.28, a:1.0}, 
-    Color {r:0.0,  g:0.03, b:0.4,  a:1.0}, 
-    Color {r:0.05, g:0.17, b:0.54, a:1.0}, 
-    Color {r:0.1,  g:0.3,  b:0.7,  a:1.0}, 
-    Color {r:0.25, g:0.5,  b:0.82, a:1.0}, 
-    Color {r:0.53, g:0.71, b:0.9,  a:1.0}, 
-    Color {r:0.83, g:0.93, b:0.97, a:1.0}, 
-    Color {r:0.95, g:0.91, b:0.75, a:1.0}, 
-    Color {r:0.97, g:0.78, b:0.37, a:1.0}, 
-    Color {r:1.0,  g:0.67, b:0.0,  a:1.0}, 
-    Color {r:0.8,  g:0.5,  b:0.0,  a:1.0}, 
-    Color {r:0.6,  g:0.34, b:0.0,  a:1.0}, 
-    Color {r:0.42, g:0.2,  b:0.02, a:1.0} 
-    ];
-const MAX_ITERATIONS: u32 = 250;
-const MAX_LENGTH: Real = 2.0;
-
-#[derive(Copy, Clone)]
-struct ThreadDescryptor {
-    offset: Vec2<usize>,
-    thread_size: Vec2<usize>,
-    color_buffer_size: Vec2<usize>,
-    sample_count: usize,
-    center: Vec2<Real>,
-    view_size: Vec2<Real>,
-}
-
-impl ThreadDescryptor {
-    fn new() -> ThreadDescryptor {
-        ThreadDescryptor {
-            offset: Vec2::<usize>::new(),
-            thread_size: Vec2::<usize>::new(),
-            color_buffer_size: Vec2::<usize>::new(),
-            sample_count: 0,
-            center: Vec2::<Real>::new(),
-            view_size: Vec2::<Real>::new(),
+    if auto_iterations.is_some() && !auto {
+        return Err(String::from("--auto-iterations requires --max-iterations auto"));
+    }
+    let auto_iterations = match auto {
+        true => Some(auto_iterations.unwrap_or(AUTO_ITERATIONS)),
+        false => None,
+    };
+    if let Some((base, per_decade)) = auto_iterations {
+        if !base.is_finite() || !per_decade.is_finite() {
+            return Err(format!("invalid value for --auto-iterations: {},{} (must be finite)", base, per_decade));
         }
+        config.set_auto_iterations(base, per_decade);
+        status!("Max iterations set to {} for the view width {}", config.max_iterations, config.view_size.x);
     }
+    if let Some(trap) = trap {
+        match config.coloring {
+            ColoringMode::OrbitTrap {..} => config.coloring = ColoringMode::OrbitTrap {trap},
+            _ => return Err(String::from("--trap requires --coloring trap")),
+        }
+    }
+    // The Julia constant combines with the power of the mandelbrot recurrence
+    match (julia, config.fractal) {
+        (Some(c), FractalKind::Mandelbrot | FractalKind::Julia {..}) => config.fractal = FractalKind::Julia {c},
+        (Some(_), _) => return Err(String::from("--julia requires --mode mandelbrot or multibrot-julia")),
+        (None, FractalKind::Julia {..}) => return Err(String::from("--mode multibrot-julia requires --julia")),
+        (None, _) => {}
+    }
+    if let Some(relax) = relax {
+        match config.fractal {
+            FractalKind::Nova {..} => config.fractal = FractalKind::Nova {relax},
+            _ => return Err(String::from("--relax requires --mode nova")),
+        }
+    }
+    config.validate()?;
+    if supersample == 0 {
+        return Err(String::from("invalid value for --supersample: 0 (must be at least 1)"));
+    }
+    if frames == Some(0) {
+        return Err(String::from("invalid value for --frames: 0 (must be at least 1)"));
+    }
+    if resume.is_some() && (frames.is_some() || matches!(config.fractal, FractalKind::Buddhabrot)) {
+        return Err(String::from("--resume can't be combined with --frames or the buddhabrot mode"));
+    }
+    if out == STDOUT_PATH && frames.is_some() {
+        return Err(String::from("--frames can't write to stdout"));
+    }
+    if iterations_out.is_some() && frames.is_some() {
+        return Err(String::from("--iterations-out can't be combined with --frames"));
+    }
+    if save.bit_depth == BitDepth::Sixteen && Path::new(&out).extension().is_some_and(|e| e.eq_ignore_ascii_case("bmp")) {
+        return Err(String::from("--depth 16 can't be saved as BMP"));
+    }
+    if apng.is_some() && frames.is_none() {
+        return Err(String::from("--animate requires --frames"));
+    }
+    if edges && (resume.is_some() || matches!(config.fractal, FractalKind::Buddhabrot)) {
+        return Err(String::from("--edges can't be combined with --resume or the buddhabrot mode"));
+    }
+    if edges && (edge_style.threshold <= 0.0 || !edge_style.threshold.is_finite()) {
+        return Err(format!("invalid value for --edge-threshold: {} (must be positive)", edge_style.threshold));
+    }
+    let edges = if edges { Some(edge_style) } else { None };
+    if preview && frames.is_some() {
+        return Err(String::from("--preview can't be combined with --frames"));
+    }
+    if keyframes.is_some() && frames.is_none() {
+        return Err(String::from("--keyframes requires --frames"));
+    }
+    let animation = frames.map(|frames| Animation {frames, zoom_start, zoom_end: zoom_end.unwrap_or(zoom), apng, delay, keyframes, auto_iterations});
+
+    Ok(Command::Render(Box::new(Options {config, out, save, animation, interactive, iterations_out, supersample, filter, resume, edges, preview, metadata, grid})))
 }
 
-fn thread_worker(color_buffer: Arc<Mutex<Vec<Color>>>, desc: ThreadDescryptor, id: usize, finishing_sender: Sender<usize>) {
-    let mut temp_color_buffer = vec![Color::new(); desc.thread_size.x * desc.thread_size.y];
-    let mut rng = rand::thread_rng();
-
-    //println!("New Thread: x {}, y {}, width {}, height {}", desc.offset.x, desc.offset.y, desc.thread_size.x, desc.thread_size.y);
-
-    // Render the fractal into the temporary color buffer
-    for y in 0..desc.thread_size.y {
-        for x in 0..desc.thread_size.x {
-            let mut pixel_color = Color::new();
-            // Stochastic Sampling
-            for _ in 0..desc.sample_count {
-                let mut norm_pos = Vec2::<Real>::new();
-                norm_pos.x = (((x+desc.offset.x) as Real) + rng.gen_range(-0.5, 0.5))/(desc.color_buffer_size.x as Real) * 2.0 - 1.0; // [-1:1]
-                norm_pos.y = -((((y+desc.offset.y) as Real) + rng.gen_range(-0.5, 0.5))/(desc.color_buffer_size.y as Real) * 2.0 - 1.0); // [-1:1]
-                let mut pos = Complex {r: 0.0, i: 0.0};
-                pos.r = desc.center.x + norm_pos.x * desc.view_size.x / 2.0; // real axis
-                pos.i = desc.center.y + norm_pos.y * desc.view_size.y / 2.0; // imaginary axis
-                let mut iterations: u32 = 0;
-                let mut temp = Complex {r: 0.0, i: 0.0};
-                while temp.length() <= MAX_LENGTH && iterations < MAX_ITERATIONS {
-                    temp = temp.squared().add(&pos);
-                    iterations += 1;
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(Command::Render(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Ok(Command::Benchmark(thread_count)) => {
+            benchmark(thread_count);
+            return;
+        }
+        Ok(Command::ListPresets) => {
+            for preset in PRESETS.iter() {
+                println!("{:<16}{} (center {},{}  zoom {}  max iterations {})", preset.name, preset.title, preset.center.x, preset.center.y, preset.zoom, preset.max_iterations);
+            }
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(1);
+        }
+    };
+    let config = &options.config;
+    install_interrupt_handler();
+
+    if options.interactive {
+        interactive::run(config, |full| render_to_file(full, &options, &options.out, None));
+        return;
+    }
+
+    match &options.animation {
+        None => {
+            if options.preview {
+                save_preview(config, &options);
+            }
+            status!("Drawing the buffer...");
+            // Renders piped to stdout are only checkpointed when resumed
+            let checkpoint_path = match &options.resume {
+                Some(resume) => Some(resume.clone()),
+                None if options.out == STDOUT_PATH => None,
+                None => Some(format!("{}.checkpoint", options.out)),
+            };
+            render_to_file(config, &options, &options.out, checkpoint_path.as_deref());
+            if let Some(path) = &options.iterations_out {
+                let iterations = render_iterations(config);
+                let region = config.output_region();
+                if let Err(error) = save_iterations(&iterations, region.width, region.height, path) {
+                    eprintln!("error: failed to save the iteration counts: {}", error);
+                    process::exit(1);
+                }
+                status!("Saved iteration counts to {}", path);
+            }
+        }
+        Some(animation) => {
+            let region = config.output_region();
+            let mut apng = animation.apng.as_ref().map(|_| Apng::new(region.width, region.height, animation.delay, &options.save));
+            // Configs of the frames added to the animated PNG, for its metadata
+            let mut apng_frames = Vec::new();
+            let start_time = time::Instant::now();
+            for frame in 0..animation.frames {
+                let t = if animation.frames > 1 { (frame as Real) / ((animation.frames - 1) as Real) } else { 0.0 };
+                let mut frame_config = config.clone();
+                match &animation.keyframes {
+                    Some(keyframes) => {
+                        let (start, end) = (keyframes[0].time, keyframes[keyframes.len() - 1].time);
+                        interpolate_keyframes(keyframes, start + (end - start) * t).apply(&mut frame_config);
+                    }
+                    None => frame_config.set_zoom(interpolate_zoom(animation.zoom_start, animation.zoom_end, t)),
+                }
+                if let Some((base, per_decade)) = animation.auto_iterations {
+                    frame_config.set_auto_iterations(base, per_decade);
                 }
-                pixel_color.add(COLOR_PALETTE[(iterations%16) as usize]);
+                status!("Drawing frame {}/{}...", frame + 1, animation.frames);
+                match &mut apng {
+                    Some(apng) => {
+                        let color_buffer = render_image(&frame_config, &options, None);
+                        // A frame cut short by Ctrl-C is left out of the animation
+                        if INTERRUPTED.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        apng.add_frame(&color_buffer);
+                        apng_frames.push(frame_config);
+                    }
+                    None => render_to_file(&frame_config, &options, &frame_path(&options.out, frame + 1), None),
+                }
+            }
+            if let (Some(apng), Some(path)) = (&apng, &animation.apng) {
+                save_animation(apng, &apng_frames, &options, start_time.elapsed(), path);
             }
-            pixel_color.divide(desc.sample_count as Real);
-            temp_color_buffer[y * desc.thread_size.x + x] = pixel_color;
         }
     }
+}
+
+// Renders the whole set with a fixed budget, so timings of different builds and machines compare
+fn benchmark(thread_count: usize) {
+    let mut config = RenderConfig::new();
+    config.width = 1280;
+    config.height = 960;
+    config.center = Vec2::<Real>{x: -0.5, y: 0.0};
+    config.set_zoom(2.5);
+    config.sample_count = 8;
+    config.max_iterations = 1000;
+    config.seed = Some(0);
+    config.thread_count = thread_count;
+    config.progress = false;
+
+    status!("Rendering the benchmark view...");
+    let (_, stats) = render_mandelbrot_with_stats(&config, &INTERRUPTED);
+    let seconds = stats.elapsed.as_secs_f64();
+    println!("wall time:         {:.3} s", seconds);
+    println!("pixels/second:     {:.0}", stats.pixels as f64 / seconds);
+    println!("iterations/second: {:.0} ({} iterations)", stats.iterations as f64 / seconds, stats.iterations);
+}
+
+// Width of the --preview image
+const PREVIEW_WIDTH: usize = 256;
+
+// Renders the whole view at PREVIEW_WIDTH with a single sample and saves it as preview.png next to --out
+fn save_preview(config: &RenderConfig, options: &Options) {
+    let mut preview = config.clone();
+    preview.width = PREVIEW_WIDTH.min(config.width);
+    preview.height = (config.height * preview.width / config.width).max(1);
+    preview.sample_count = 1;
+    preview.region = None;
+
+    let path = match Path::new(&options.out).parent() {
+        Some(parent) if options.out != STDOUT_PATH => parent.join("preview.png").to_string_lossy().into_owned(),
+        _ => String::from("preview.png"),
+    };
+    status!("Drawing the preview...");
+    let mut color_buffer = match &options.edges {
+        Some(style) => render_edges(&preview, style),
+        None => render_mandelbrot_interruptible(&preview, &INTERRUPTED),
+    };
+    if options.grid {
+        draw_grid(&mut color_buffer, &preview);
+    }
+    if let Err(error) = save_image_auto(&color_buffer, preview.width, preview.height, &path, &options.save) {
+        eprintln!("error: failed to save the preview: {}", error);
+        process::exit(1);
+    }
+    exit_if_interrupted(&path);
+    status!("\nSaved preview to {}", path);
+}
+
+// Exits after the partial image was saved if the render was interrupted
+fn exit_if_interrupted(path: &str) {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        status!("Render interrupted, saved the partial image to {}", path);
+        process::exit(130);
+    }
+}
+
+// Path of an animation frame, frame_<index> with the extension of out in the directory of out
+fn frame_path(out: &str, index: usize) -> String {
+    let out = Path::new(out);
+    let extension = out.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let name = format!("frame_{:04}.{}", index, extension);
+    match out.parent() {
+        Some(parent) => parent.join(name).to_string_lossy().into_owned(),
+        None => name,
+    }
+}
 
-    // copy the temporary color buffer after locking the color_buffer mutex
-    let mut cb = color_buffer.lock().unwrap();
-    for y in 0..desc.thread_size.y {
-        for x in 0..desc.thread_size.x {
-            cb[(y+desc.offset.y) * desc.color_buffer_size.x + (x+desc.offset.x)] = temp_color_buffer[y * desc.thread_size.x + x];
+// Renders config while saving its progress to checkpoint_path, resuming from --resume if given.
+// The checkpoint is removed once every tile is rendered
+fn render_checkpointed(config: &RenderConfig, options: &Options, checkpoint_path: &str) -> (Vec<Color>, RenderStats) {
+    let mut checkpoint = match &options.resume {
+        Some(resume) => match Checkpoint::load(resume, config) {
+            Ok(checkpoint) => checkpoint,
+            Err(error) => {
+                eprintln!("error: failed to resume: {}", error);
+                process::exit(1);
+            }
+        },
+        None => Checkpoint::new(config),
+    };
+
+    let stats = render_mandelbrot_resumable(config, &INTERRUPTED, &mut checkpoint, |checkpoint| {
+        if let Err(error) = checkpoint.save(checkpoint_path) {
+            eprintln!("\nwarning: failed to save the checkpoint: {}", error);
         }
+    });
+
+    if checkpoint.is_complete() {
+        let _ = fs::remove_file(checkpoint_path);
     }
+    else {
+        status!("\nSaved checkpoint to {}, continue with --resume {}", checkpoint_path, checkpoint_path);
+    }
+    (checkpoint.color_buffer, stats)
+}
 
-    finishing_sender.send(id).unwrap();
+// Saves the frames rendered so far, exits the process if the animation can't be saved
+fn save_animation(apng: &Apng, frames: &[RenderConfig], options: &Options, elapsed: time::Duration, path: &str) {
+    if INTERRUPTED.load(Ordering::SeqCst) && apng.frame_count() == 0 {
+        status!("Render interrupted before the first frame was finished, nothing was saved");
+        process::exit(130);
+    }
+    if let Err(error) = apng.save(path) {
+        eprintln!("error: failed to save the animation: {}", error);
+        process::exit(1);
+    }
+    if options.metadata {
+        save_sidecar(&animation_metadata(frames, apng.delay(), &output_settings(options), elapsed), path);
+    }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        status!("Render interrupted, saved the first {} frames to {}", apng.frame_count(), path);
+        process::exit(130);
+    }
+    status!("Saved animation to {}", path);
 }
 
-fn main() {
-    const BUFFER_WIDTH: usize = 1366; // 7680; // 3840; // 1366;
-    const BUFFER_HEIGHT: usize = 768; // 4320; // 2160; // 768;
-    const BUFFER_ASPECT_RATIO: Real = (BUFFER_WIDTH as Real) / (BUFFER_HEIGHT as Real);
-    const SAMPLE_COUNT: usize = 16;
-    const CENTER_X: Real = -0.7453;
-    const CENTER_Y: Real = 0.1127;
-    const VIEW_WIDTH: Real = 6.5E-4 * BUFFER_ASPECT_RATIO;
-    const VIEW_HEIGHT: Real = 6.5E-4;
-    const THREAD_WIDTH: usize = 128;
-    const THREAD_HEIGHT: usize = 128;
-    const THREAD_COUNT: usize = 4;
-
-    // Row major
-    let color_buffer = Arc::new(Mutex::new(vec![Color::new(); BUFFER_WIDTH * BUFFER_HEIGHT]));
-
-    println!("Drawing the buffer...");
-    print_progress(0);
+// Renders config at the output resolution, tiled renders with a checkpoint path are checkpointed there while they run
+fn render_image(config: &RenderConfig, options: &Options, checkpoint_path: Option<&str>) -> Vec<Color> {
     let start_time = time::Instant::now();
 
-    // Fill threads descryptors
-    let mut threads_descryptors = Vec::new();
-    for y in 0..divide_roundup(BUFFER_HEIGHT, THREAD_HEIGHT) {
-        for x in 0..divide_roundup(BUFFER_WIDTH, THREAD_WIDTH) {
-            let mut new_desc = ThreadDescryptor::new();
-            new_desc.offset = Vec2::<usize>{x: x * THREAD_WIDTH, y: y * THREAD_HEIGHT};
-            let max_width = BUFFER_WIDTH - x*THREAD_WIDTH;
-            let max_height = BUFFER_HEIGHT - y*THREAD_HEIGHT;
-            new_desc.thread_size = Vec2::<usize>{x: clamp(THREAD_WIDTH, 0, max_width-1), y: clamp(THREAD_HEIGHT, 0, max_height-1)};
-            new_desc.color_buffer_size = Vec2::<usize>{x: BUFFER_WIDTH, y: BUFFER_HEIGHT};
-            new_desc.sample_count = SAMPLE_COUNT;
-            new_desc.center = Vec2::<Real>{x: CENTER_X, y:CENTER_Y};
-            new_desc.view_size = Vec2::<Real>{x: VIEW_WIDTH, y:VIEW_HEIGHT};
-            threads_descryptors.push(new_desc);
+    // Supersampling renders the same view with factor times the pixels in each direction
+    let mut render_config = config.clone();
+    render_config.width *= options.supersample;
+    render_config.height *= options.supersample;
+    render_config.region = config.region.map(|region| Region {
+        x: region.x * options.supersample,
+        y: region.y * options.supersample,
+        width: region.width * options.supersample,
+        height: region.height * options.supersample,
+    });
+    let (color_buffer, stats) = match checkpoint_path {
+        _ if options.edges.is_some() => (render_edges(&render_config, options.edges.as_ref().unwrap()), None),
+        Some(checkpoint_path) if !matches!(config.fractal, FractalKind::Buddhabrot) => {
+            let (color_buffer, stats) = render_checkpointed(&render_config, options, checkpoint_path);
+            (color_buffer, Some(stats))
+        }
+        _ => {
+            let (color_buffer, stats) = render_mandelbrot_with_stats(&render_config, &INTERRUPTED);
+            (color_buffer, Some(stats))
         }
+    };
+    let region = config.output_region();
+    let mut color_buffer = downsample(&color_buffer, region.width, region.height, options.supersample, options.filter);
+    // Drawn at the output resolution so the lines stay a pixel thin
+    if options.grid {
+        draw_grid(&mut color_buffer, config);
     }
 
-    // Spawn threads
-    let mut next_thread = 0usize;
-    let mut threads = Vec::<thread::JoinHandle<()>>::new();
-    let (sender, receiver) = channel::<usize>();
-    // Spawn THREAD_COUNT thread first
-    for i in 0..min(threads_descryptors.len(), THREAD_COUNT) {
-        let descryptor = threads_descryptors[i];
-        let color_buffer_clone = color_buffer.clone();
-        let sender_clone = sender.clone();
-        threads.push(thread::spawn(move || thread_worker(color_buffer_clone, descryptor, i, sender_clone)));
-        next_thread += 1;
-    }
-    let mut finished_threads = 0usize;
-    while finished_threads < threads_descryptors.len() {
-        let finished_id = receiver.recv().unwrap();
-        finished_threads += 1;
-        let progress = finished_threads*100/threads_descryptors.len();
-        print_progress(progress as u32);
-        // print!("{}/{}   ", finished_threads, threads_descryptors.len()); // thread number
-        // Spawn a new thread if needed
-        if next_thread < threads_descryptors.len() {
-            let descryptor = threads_descryptors[next_thread];
-            let color_buffer_clone = color_buffer.clone();
-            let sender_clone = sender.clone();
-            // Replace the finished thread handle
-            threads[finished_id] = thread::spawn(move || thread_worker(color_buffer_clone, descryptor, finished_id, sender_clone));
-            next_thread += 1;
+    let duration = time::Instant::now().duration_since(start_time).as_secs();
+    // The progress bar leaves the cursor at the end of its line
+    status!("\nFinished rendering in {}h{}m{}s", (duration/60/60), (duration/60)%60, duration%60);
+    if let Some(stats) = stats {
+        let sampling = match (config.adaptive_threshold, config.detail_threshold) {
+            (Some(_), _) => Some("Adaptive"),
+            (None, Some(_)) => Some("Detail"),
+            (None, None) => None,
+        };
+        if let (Some(sampling), true) = (sampling, stats.pixels > 0) {
+            status!("{} sampling took {} samples, {:.2} per pixel on average", sampling, stats.samples, stats.samples as f64 / stats.pixels as f64);
         }
     }
+    color_buffer
+}
 
-    // join all threads
-    for thread in threads {
-        thread.join().unwrap();
+// Settings of options applied to the rendered buffer before it is saved, for the metadata
+fn output_settings(options: &Options) -> OutputSettings {
+    OutputSettings {supersample: options.supersample, filter: options.filter, edges: options.edges, grid: options.grid, save: options.save}
+}
+
+// Writes the metadata sidecar of the image at path, a failure only warns
+fn save_sidecar(metadata: &str, path: &str) {
+    let metadata_path = Path::new(path).with_extension("json").to_string_lossy().into_owned();
+    if let Err(error) = save_metadata(metadata, &metadata_path) {
+        eprintln!("warning: failed to save the render metadata: {}", error);
     }
+}
 
-    let duration = time::Instant::now().duration_since(start_time).as_secs();
-    println!("\nFinished rendering in {}h{}m{}s", (duration/60/60), (duration/60)%60, duration%60);
+// Renders config and saves it to path, exits the process if the image can't be saved
+fn render_to_file(config: &RenderConfig, options: &Options, path: &str, checkpoint_path: Option<&str>) {
+    let start_time = time::Instant::now();
+    let color_buffer = render_image(config, options, checkpoint_path);
+    let elapsed = start_time.elapsed();
+    let region = config.output_region();
+    if let Err(error) = save_image_auto(&color_buffer, region.width, region.height, path, &options.save) {
+        eprintln!("error: failed to save the image: {}", error);
+        process::exit(1);
+    }
+    if options.metadata && path != STDOUT_PATH {
+        save_sidecar(&render_metadata(config, &output_settings(options), elapsed), path);
+    }
+    exit_if_interrupted(path);
+    if path == STDOUT_PATH {
+        status!("Wrote the image to stdout");
+        return;
+    }
+    status!("Saved buffer to {}", path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let cb = color_buffer.lock().unwrap();
-    save_image(&cb, BUFFER_WIDTH, BUFFER_HEIGHT, "output/image.png");
-    println!("Saved buffer to image.png");
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn animations_need_a_frame() {
+        let error = parse_args(&args("--frames 0")).err().unwrap();
+        assert!(error.contains("--frames"), "{}", error);
+        assert!(parse_args(&args("--frames 0 --animate a.png")).is_err());
+        match parse_args(&args("--frames 1")) {
+            Ok(Command::Render(options)) => assert_eq!(options.animation.map(|animation| animation.frames), Some(1)),
+            _ => panic!("--frames 1 is a valid animation"),
+        }
+    }
 }
@@ -0,0 +1,194 @@
+// JSON sidecar describing how an image was rendered, so it can be reproduced or zoomed into later
+use std::fs;
+use std::io;
+use std::time;
+use super::{Color, EdgeStyle, RenderConfig, Real, ResampleFilter, SaveOptions, checkpoint, with_path};
+
+// FNV-1a, stable across builds and platforms unlike the std hashers
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// Debug output of the enums as a JSON string
+fn json_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn json_pair(x: Real, y: Real) -> String {
+    format!("[{}, {}]", x, y)
+}
+
+fn json_color(color: &Color) -> String {
+    format!("[{}, {}, {}]", color.r, color.g, color.b)
+}
+
+// How the rendered buffer was turned into the saved image, these change its pixels as much as the config
+#[derive(Copy, Clone, Debug)]
+pub struct OutputSettings {
+    // Supersampling factor and the filter the buffer was shrunk with
+    pub supersample: usize,
+    pub filter: ResampleFilter,
+    pub edges: Option<EdgeStyle>,
+    pub grid: bool,
+    pub save: SaveOptions,
+}
+
+impl OutputSettings {
+    pub fn new() -> OutputSettings {
+        OutputSettings {supersample: 1, filter: ResampleFilter::Box, edges: None, grid: false, save: SaveOptions::new()}
+    }
+}
+
+impl Default for OutputSettings {
+    fn default() -> OutputSettings {
+        OutputSettings::new()
+    }
+}
+
+// Fields of a render of config, config_hash is the hash of hashed
+fn fields(config: &RenderConfig, output: &OutputSettings, hashed: &str) -> Vec<(&'static str, String)> {
+    let palette: Vec<String> = config.palette.iter().map(|color| format!("[{}, {}, {}, {}]", color.r, color.g, color.b, color.a)).collect();
+    let region = match config.region {
+        Some(region) => format!("[{}, {}, {}, {}]", region.x, region.y, region.width, region.height),
+        None => String::from("null"),
+    };
+    let optional = |value: Option<String>| value.unwrap_or_else(|| String::from("null"));
+    let edges = output.edges.map(|style| format!("{{\"line\": {}, \"background\": {}, \"threshold\": {}}}", json_color(&style.line), json_color(&style.background), style.threshold));
+    let buddhabrot = &config.buddhabrot;
+    vec![
+        ("version", json_string(env!("CARGO_PKG_VERSION"))),
+        ("config_hash", json_string(&format!("{:016x}", fnv1a(hashed)))),
+        ("center", json_pair(config.center.x, config.center.y)),
+        ("center_digits", optional(config.center_digits.as_ref().map(|(x, y)| format!("[{}, {}]", json_string(x), json_string(y))))),
+        ("view_size", json_pair(config.view_size.x, config.view_size.y)),
+        ("flip_y", config.flip_y.to_string()),
+        ("width", config.width.to_string()),
+        ("height", config.height.to_string()),
+        ("region", region),
+        ("tile_size", config.tile_size.to_string()),
+        ("samples", config.sample_count.to_string()),
+        ("sampling", json_string(&format!("{:?}", config.sampling))),
+        ("samples_adaptive", optional(config.adaptive_threshold.map(|threshold| threshold.to_string()))),
+        ("samples_detail", optional(config.detail_threshold.map(|threshold| threshold.to_string()))),
+        ("fast_fill", config.fast_fill.to_string()),
+        ("debug_tiles", config.debug_tiles.to_string()),
+        ("seed", optional(config.seed.map(|seed| seed.to_string()))),
+        ("fractal", json_string(&format!("{:?}", config.fractal))),
+        ("exponent", config.exponent.to_string()),
+        ("max_iterations", config.max_iterations.to_string()),
+        ("bailout", config.bailout.to_string()),
+        ("buddhabrot", format!("{{\"min_iterations\": {}, \"max_iterations\": {:?}}}", buddhabrot.min_iterations, buddhabrot.max_iterations)),
+        ("coloring", json_string(&format!("{:?}", config.coloring))),
+        ("color_scale", json_string(&format!("{:?}", config.color_scale))),
+        ("light", format!("{{\"angle\": {}, \"height\": {}}}", config.light.angle, config.light.height)),
+        ("interior", json_string(&format!("{:?}", config.interior))),
+        ("palette", format!("[{}]", palette.join(", "))),
+        ("inside_color", json_color(&config.inside_color)),
+        ("palette_offset", config.palette_offset.to_string()),
+        ("color_density", config.color_density.to_string()),
+        ("gamma", config.gamma.to_string()),
+        ("color_space", json_string(&format!("{:?}", config.color_space))),
+        ("deep_zoom", config.deep_zoom.to_string()),
+        ("precision", optional(config.precision.map(|bits| bits.to_string()))),
+        ("supersample", output.supersample.to_string()),
+        ("filter", json_string(&format!("{:?}", output.filter))),
+        ("edges", optional(edges)),
+        ("grid", output.grid.to_string()),
+        ("bit_depth", json_string(&format!("{:?}", output.save.bit_depth))),
+        ("save_gamma", output.save.gamma.to_string()),
+        ("grayscale", output.save.grayscale.to_string()),
+        ("dither", output.save.dither.to_string()),
+        ("exposure", output.save.exposure.to_string()),
+        ("tone_map", json_string(&format!("{:?}", output.save.tone_map))),
+    ]
+}
+
+fn json_object(fields: &[(&str, String)]) -> String {
+    let lines: Vec<String> = fields.iter().map(|(key, value)| format!("  \"{}\": {}", key, value)).collect();
+    format!("{{\n{}\n}}\n", lines.join(",\n"))
+}
+
+// Metadata of a render of config saved with output that took elapsed, config_hash is the
+// same for every render of the same pixels
+pub fn render_metadata(config: &RenderConfig, output: &OutputSettings, elapsed: time::Duration) -> String {
+    let hashed = format!("{}{:?}", checkpoint::fingerprint(config), output);
+    let mut fields = fields(config, output, &hashed);
+    fields.push(("render_seconds", elapsed.as_secs_f64().to_string()));
+    json_object(&fields)
+}
+
+// Metadata of an animated PNG of the configs of frames shown for delay milliseconds each, the
+// settings are those of the first frame and the view of every frame is listed in frames
+pub fn animation_metadata(frames: &[RenderConfig], delay: u16, output: &OutputSettings, elapsed: time::Duration) -> String {
+    let mut hashed = format!("{:?}{}", output, delay);
+    for frame in frames {
+        hashed.push_str(&checkpoint::fingerprint(frame));
+    }
+    let views: Vec<String> = frames.iter().map(|frame| format!("    {{\"center\": {}, \"view_size\": {}, \"max_iterations\": {}, \"palette_offset\": {}}}",
+        json_pair(frame.center.x, frame.center.y), json_pair(frame.view_size.x, frame.view_size.y), frame.max_iterations, frame.palette_offset)).collect();
+    let mut fields = match frames.first() {
+        Some(first) => fields(first, output, &hashed),
+        None => vec![],
+    };
+    fields.push(("frame_delay", delay.to_string()));
+    fields.push(("frames", format!("[\n{}\n  ]", views.join(",\n"))));
+    fields.push(("render_seconds", elapsed.as_secs_f64().to_string()));
+    json_object(&fields)
+}
+
+// Writes metadata, of render_metadata or animation_metadata, to path
+pub fn save_metadata(metadata: &str, path: &str) -> io::Result<()> {
+    fs::write(path, metadata).map_err(|e| with_path(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ToneMap;
+
+    #[test]
+    fn hash_only_follows_the_rendered_pixels() {
+        let mut config = RenderConfig::new();
+        let mut output = OutputSettings::new();
+        let hash = |config: &RenderConfig, output: &OutputSettings| render_metadata(config, output, time::Duration::from_secs(1)).lines().nth(2).unwrap().to_string();
+        let first = hash(&config, &output);
+        config.thread_count = 3;
+        config.progress = false;
+        assert_eq!(hash(&config, &output), first);
+        config.max_iterations += 1;
+        assert_ne!(hash(&config, &output), first);
+
+        // Settings applied after the render change the saved pixels too
+        let config = RenderConfig::new();
+        let changes: [fn(&mut OutputSettings); 5] = [
+            |output| output.supersample = 2,
+            |output| output.grid = true,
+            |output| output.edges = Some(EdgeStyle::new()),
+            |output| output.save.exposure = 1.0,
+            |output| output.save.dither = true,
+        ];
+        for change in changes.iter() {
+            let mut changed = output;
+            change(&mut changed);
+            assert_ne!(hash(&config, &changed), first);
+        }
+        output.save.tone_map = ToneMap::Aces;
+        assert!(render_metadata(&config, &output, time::Duration::from_secs(1)).contains("\"tone_map\": \"Aces\""));
+    }
+
+    #[test]
+    fn animations_list_the_view_of_every_frame() {
+        let mut frames = vec![RenderConfig::new(), RenderConfig::new()];
+        frames[1].set_zoom(0.5);
+        frames[1].max_iterations = 400;
+        let output = OutputSettings::new();
+        let metadata = animation_metadata(&frames, 40, &output, time::Duration::from_secs(2));
+        assert!(metadata.contains("\"frame_delay\": 40"));
+        assert_eq!(metadata.matches("\"max_iterations\": 400").count(), 1);
+        assert_eq!(metadata.matches("{\"center\": ").count(), 2);
+        assert!(metadata.starts_with("{\n") && metadata.ends_with("\n}\n"));
+        let hash = |frames: &[RenderConfig], delay: u16| animation_metadata(frames, delay, &output, time::Duration::from_secs(1)).lines().nth(2).unwrap().to_string();
+        assert_ne!(hash(&frames, 40), hash(&frames, 80));
+        assert_ne!(hash(&frames, 40), hash(&frames[..1], 40));
+    }
+}
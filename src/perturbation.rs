@@ -0,0 +1,181 @@
+// Perturbation rendering for deep zooms: one reference orbit per tile is iterated in f64 and
+// every sample only iterates its small difference to it, so positions closer together than the
+// precision of the coordinates still get their own orbits
+use std::sync::atomic::AtomicUsize;
+use rand::Rng;
+use super::{Color, ColoringMode, Complex, InteriorColoring, Orbit, Palette, Prepass, Real, ThreadDescryptor, Work, in_main_cardioid_or_bulb, shade_rows};
+
+// f64 complex number of the reference orbit and the deltas, independent of Real
+#[derive(Copy, Clone, Debug)]
+struct Complex64 {
+    r: f64,
+    i: f64,
+}
+
+impl Complex64 {
+    fn add(self, other: Complex64) -> Complex64 {
+        Complex64 {r: self.r + other.r, i: self.i + other.i}
+    }
+
+    fn sub(self, other: Complex64) -> Complex64 {
+        Complex64 {r: self.r - other.r, i: self.i - other.i}
+    }
+
+    fn mul(self, other: Complex64) -> Complex64 {
+        Complex64 {r: self.r*other.r - self.i*other.i, i: self.r*other.i + self.i*other.r}
+    }
+
+    fn length_squared(self) -> f64 {
+        self.r*self.r + self.i*self.i
+    }
+
+    fn to_complex(self) -> Complex {
+        Complex {r: self.r as Real, i: self.i as Real}
+    }
+}
+
+// Candidate reference points per tile side, see ReferenceOrbit::longest
+const REFERENCE_CANDIDATES: usize = 5;
+
+// Offset from the view center of the point at pixel coordinates x, y
+#[allow(clippy::unnecessary_cast)]
+fn center_offset(desc: &ThreadDescryptor, x: f64, y: f64) -> Complex64 {
+    let size = &desc.color_buffer_size;
+    Complex64 {
+        r: (x / size.x as f64 - 0.5) * desc.view_size.x as f64,
+        i: -(y / size.y as f64 - 0.5) * desc.view_size.y as f64,
+    }
+}
+
+// Orbit of a point close to pixel coordinates x, y, up to the iteration it escapes at or max_iterations
+pub(crate) struct ReferenceOrbit {
+    c: Complex64,
+    // Exact offset of c from the view center, c itself is rounded to f64
+    offset: Complex64,
+    z: Vec<Complex64>,
+}
+
+impl ReferenceOrbit {
+    #[allow(clippy::unnecessary_cast)]
+    fn new(desc: &ThreadDescryptor, x: f64, y: f64) -> ReferenceOrbit {
+        let center = Complex64 {r: desc.center.x as f64, i: desc.center.y as f64};
+        let c = center.add(center_offset(desc, x, y));
+        // The difference of two close floats is exact, so every tile's deltas are measured
+        // from the same center and tiles line up even when the rounding of c spans pixels
+        let offset = c.sub(center);
+        let bailout_squared = (desc.bailout as f64) * (desc.bailout as f64);
+        let mut z = vec![Complex64 {r: 0.0, i: 0.0}];
+        while z.len() <= desc.max_iterations as usize && z[z.len() - 1].length_squared() <= bailout_squared {
+            let last = z[z.len() - 1];
+            z.push(last.mul(last).add(c));
+        }
+        ReferenceOrbit {c, offset, z}
+    }
+
+    // Longest orbit of a grid of points over the tile of desc and the view center, which deep zooms
+    // usually aim at the interior. Samples outliving the reference continue from its start at full
+    // magnitude and lose the precision of their delta, so it should escape as late as possible
+    pub(crate) fn longest(desc: &ThreadDescryptor) -> ReferenceOrbit {
+        let size = &desc.color_buffer_size;
+        let mut longest = ReferenceOrbit::new(desc, size.x as f64 / 2.0, size.y as f64 / 2.0);
+        for j in 0..REFERENCE_CANDIDATES {
+            for i in 0..REFERENCE_CANDIDATES {
+                let x = desc.offset.x as f64 + desc.thread_size.x as f64 * (i as f64 + 0.5) / REFERENCE_CANDIDATES as f64;
+                let y = desc.offset.y as f64 + desc.thread_size.y as f64 * (j as f64 + 0.5) / REFERENCE_CANDIDATES as f64;
+                let candidate = ReferenceOrbit::new(desc, x, y);
+                if candidate.z.len() > longest.z.len() {
+                    longest = candidate;
+                }
+            }
+        }
+        longest
+    }
+
+    // Iterates the sample at pixel coordinates x, y as a delta to the reference, same as iterate
+    // without the periodicity check. z_n = Z_n + delta_n with delta_(n+1) = (2*Z_n + delta_n)*delta_n + dc.
+    // Once z gets smaller than delta or the reference ends, the delta continues from the start of the
+    // reference with delta = z, which keeps it small enough to stay accurate
+    #[allow(clippy::unnecessary_cast)]
+    pub(crate) fn iterate(&self, desc: &ThreadDescryptor, x: f64, y: f64) -> Orbit {
+        let dc = center_offset(desc, x, y).sub(self.offset);
+        let track_derivative = matches!(desc.coloring, ColoringMode::Distance | ColoringMode::Slope);
+        let trap = match desc.coloring {
+            ColoringMode::OrbitTrap {trap} => Some(trap),
+            _ => None,
+        };
+        let mut trap_distance = Real::INFINITY;
+        let zero = Complex64 {r: 0.0, i: 0.0};
+        let bailout_squared = (desc.bailout as f64) * (desc.bailout as f64);
+
+        if trap.is_none() && desc.interior == InteriorColoring::Flat && in_main_cardioid_or_bulb(&self.c.add(dc).to_complex()) {
+            return Orbit {iterations: desc.max_iterations, computed: 0, escaped: false, z: zero.to_complex(), dz: zero.to_complex(), trap_distance, interior_distance: None};
+        }
+
+        let mut delta = zero;
+        let mut z = zero;
+        let mut dz = zero;
+        let mut reference_index = 0;
+        let mut iterations = 0;
+        while iterations < desc.max_iterations {
+            if track_derivative {
+                // dz = 2*z*dz + 1
+                dz = Complex64 {r: 2.0, i: 0.0}.mul(z).mul(dz).add(Complex64 {r: 1.0, i: 0.0});
+            }
+            let reference = self.z[reference_index];
+            delta = reference.add(reference).add(delta).mul(delta).add(dc);
+            reference_index += 1;
+            iterations += 1;
+            z = self.z[reference_index].add(delta);
+            if let Some(trap) = trap {
+                trap_distance = trap_distance.min(trap.distance(&z.to_complex()));
+            }
+
+            if z.length_squared() > bailout_squared {
+                break;
+            }
+            if reference_index == self.z.len() - 1 || z.length_squared() < delta.length_squared() {
+                delta = z;
+                reference_index = 0;
+            }
+        }
+        let escaped = z.length_squared() > bailout_squared;
+        Orbit {iterations, computed: iterations, escaped, z: z.to_complex(), dz: dz.to_complex(), trap_distance, interior_distance: None}
+    }
+}
+
+// Same as thread_worker with the samples iterated against a reference orbit inside the tile
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn thread_worker<R: Rng>(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, palette: &Palette, prepass: &Prepass, pixels_done: &AtomicUsize, rng: &mut R) -> Work {
+    let reference = ReferenceOrbit::longest(desc);
+    shade_rows(rows, desc, palette, prepass, pixels_done, rng, |x, y, jitter| reference.iterate(desc, x as f64 + jitter.x as f64, y as f64 + jitter.y as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{RenderConfig, Vec2, iterate, pixel_position};
+
+    #[test]
+    fn deltas_follow_the_direct_iteration() {
+        let mut config = RenderConfig::new();
+        config.width = 64;
+        config.height = 48;
+        config.center = Vec2::<Real>{x: -0.75, y: 0.1};
+        config.set_zoom(0.05);
+        config.max_iterations = 500;
+        let desc = ThreadDescryptor::from_config(&config);
+        let reference = ReferenceOrbit::new(&desc, 32.0, 24.0);
+        let mut matching = 0;
+        for y in 0..config.height {
+            for x in 0..config.width {
+                let expected = iterate(&desc, &pixel_position(&desc, x, y, Vec2::<Real>::new()));
+                let orbit = reference.iterate(&desc, x as f64, y as f64);
+                if orbit.escaped == expected.escaped && orbit.iterations == expected.iterations {
+                    matching += 1;
+                }
+            }
+        }
+        // Rounding differs between the two, so a few orbits close to the boundary part ways
+        assert!(matching * 100 >= config.width * config.height * 95, "{} matching", matching);
+    }
+}
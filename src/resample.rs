@@ -0,0 +1,143 @@
+// Shrinking a supersampled buffer down to the output resolution
+use super::{Color, Real};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResampleFilter {
+    // Plain average of the factor*factor block of every output pixel
+    Box,
+    // Windowed sinc over 3 output pixels on each side, sharper but may ring slightly
+    Lanczos3,
+}
+
+fn sinc(x: Real) -> Real {
+    if x.abs() < 1e-6 {
+        return 1.0;
+    }
+    let x = x * std::f64::consts::PI as Real;
+    x.sin() / x
+}
+
+fn lanczos3(x: Real) -> Real {
+    if x.abs() >= 3.0 {
+        return 0.0;
+    }
+    sinc(x) * sinc(x / 3.0)
+}
+
+// Source indices and normalized weights contributing to every output index along one axis
+fn axis_weights(size: usize, factor: usize, filter: ResampleFilter) -> Vec<Vec<(usize, Real)>> {
+    let source_size = size * factor;
+    (0..size).map(|output| {
+        let mut weights = Vec::new();
+        match filter {
+            ResampleFilter::Box => {
+                for source in output*factor..(output + 1)*factor {
+                    weights.push((source, 1.0));
+                }
+            }
+            ResampleFilter::Lanczos3 => {
+                // Output pixel centers in source pixel coordinates, the kernel is stretched by factor
+                let center = (output as Real + 0.5) * (factor as Real) - 0.5;
+                let radius = 3.0 * factor as Real;
+                let first = (center - radius).floor().max(0.0) as usize;
+                let last = ((center + radius).ceil() as usize).min(source_size - 1);
+                for source in first..=last {
+                    let weight = lanczos3((source as Real - center) / (factor as Real));
+                    if weight != 0.0 {
+                        weights.push((source, weight));
+                    }
+                }
+            }
+        }
+        let total: Real = weights.iter().map(|&(_, weight)| weight).sum();
+        weights.iter().map(|&(source, weight)| (source, weight / total)).collect()
+    }).collect()
+}
+
+fn weighted_sum(colors: impl Iterator<Item = (Color, Real)>) -> Color {
+    let mut sum = Color::new();
+    for (color, weight) in colors {
+        sum = sum + color * weight;
+    }
+    sum
+}
+
+// Shrinks a buffer rendered at factor times width x height down to width x height,
+// the filter is applied separably, first along rows then along columns
+pub fn downsample(color_buffer: &[Color], width: usize, height: usize, factor: usize, filter: ResampleFilter) -> Vec<Color> {
+    if factor <= 1 {
+        return color_buffer.to_vec();
+    }
+    let source_width = width * factor;
+    let source_height = height * factor;
+    let x_weights = axis_weights(width, factor, filter);
+    let y_weights = axis_weights(height, factor, filter);
+
+    let mut rows = Vec::with_capacity(width * source_height);
+    for y in 0..source_height {
+        let source_row = &color_buffer[y * source_width..(y + 1) * source_width];
+        for weights in &x_weights {
+            rows.push(weighted_sum(weights.iter().map(|&(x, weight)| (source_row[x], weight))));
+        }
+    }
+
+    let mut output = Vec::with_capacity(width * height);
+    for weights in &y_weights {
+        for x in 0..width {
+            output.push(weighted_sum(weights.iter().map(|&(y, weight)| (rows[y * width + x], weight))));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Color, b: Color) -> bool {
+        (a.r - b.r).abs() < 1e-5 && (a.g - b.g).abs() < 1e-5 && (a.b - b.b).abs() < 1e-5 && (a.a - b.a).abs() < 1e-5
+    }
+
+    #[test]
+    fn constant_images_stay_constant() {
+        let color = Color::rgb(0.25, 0.5, 0.75);
+        // Odd sizes and a single pixel wide column clip the Lanczos kernel on every side
+        for (width, height, factor) in [(4, 3, 2), (5, 7, 3), (1, 6, 4), (6, 1, 3), (1, 1, 5)] {
+            for filter in [ResampleFilter::Box, ResampleFilter::Lanczos3] {
+                let source = vec![color; width * factor * height * factor];
+                let output = downsample(&source, width, height, factor, filter);
+                assert_eq!(output.len(), width * height);
+                assert!(output.iter().all(|&pixel| close(pixel, color)), "{}x{} by {} with {:?}", width, height, factor, filter);
+            }
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_every_block() {
+        // 4x2 source of distinct values, shrunk to 2x1
+        let values = [0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+        let source: Vec<Color> = values.iter().map(|&value| Color::rgb(value, 0.0, 0.0)).collect();
+        let output = downsample(&source, 2, 1, 2, ResampleFilter::Box);
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].r, (0.0 + 1.0 + 8.0 + 16.0) / 4.0);
+        assert_eq!(output[1].r, (2.0 + 4.0 + 32.0 + 64.0) / 4.0);
+        assert_eq!(output[0].a, 1.0);
+    }
+
+    #[test]
+    fn lanczos_weights_stay_inside_the_source() {
+        for size in 1..8 {
+            for factor in 2..5 {
+                let weights = axis_weights(size, factor, ResampleFilter::Lanczos3);
+                for output in &weights {
+                    assert!(output.iter().all(|&(source, _)| source < size * factor));
+                    let total: Real = output.iter().map(|&(_, weight)| weight).sum();
+                    assert!((total - 1.0).abs() < 1e-5);
+                }
+            }
+        }
+        // A single source pixel per output keeps it as it is
+        let source = vec![Color::rgb(0.5, 0.0, 1.0); 3];
+        assert_eq!(downsample(&source, 1, 3, 1, ResampleFilter::Lanczos3), source);
+    }
+}
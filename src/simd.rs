@@ -0,0 +1,139 @@
+// Escape time iteration of LANES samples at once with portable SIMD, only built
+// with the simd feature on a nightly compiler. Lanes that escape are masked out
+// and keep their final z while the others carry on
+use std::simd::{Mask, Simd, prelude::*};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rand::Rng;
+use super::{Color, ColoringMode, Complex, FractalKind, InteriorColoring, Orbit, Palette, Real, ThreadDescryptor, Work, PERIODICITY_CHECK_INTERVAL, in_main_cardioid_or_bulb, pixel_position, sample_color, sample_offset};
+
+const LANES: usize = 8;
+
+type Lanes = Simd<Real, LANES>;
+
+// The vector path covers the quadratic Mandelbrot set with the colorings that
+// only need the escape time and the final z of escaping samples
+pub(crate) fn supports(desc: &ThreadDescryptor) -> bool {
+    let plain_coloring = matches!(desc.coloring, ColoringMode::Banded | ColoringMode::Smooth | ColoringMode::Histogram | ColoringMode::Grayscale);
+    matches!(desc.fractal, FractalKind::Mandelbrot) && desc.exponent == 2 && plain_coloring && desc.interior == InteriorColoring::Flat && !desc.deep_zoom && desc.precision.is_none() && desc.adaptive_threshold.is_none() && !desc.fast_fill
+}
+
+// Iterates up to LANES samples and gives the same orbits as iterate. Every lane starts
+// at the same time, so the periodicity check moves the reference of all lanes together
+fn iterate_lanes(desc: &ThreadDescryptor, positions: &[Complex], orbits: &mut Vec<Orbit>) {
+    let mut c_r = [0.0; LANES];
+    let mut c_i = [0.0; LANES];
+    let mut inside = [false; LANES];
+    for (lane, pos) in positions.iter().enumerate() {
+        c_r[lane] = pos.r;
+        c_i[lane] = pos.i;
+        inside[lane] = in_main_cardioid_or_bulb(pos);
+    }
+    let (c_r, c_i) = (Lanes::from_array(c_r), Lanes::from_array(c_i));
+    let bailout_squared = Lanes::splat(desc.bailout*desc.bailout);
+
+    // Unused lanes past the end of positions and samples in the cardioid or bulb start inactive
+    let mut active = !Mask::from_array(inside);
+    for lane in positions.len()..LANES {
+        active.set(lane, false);
+    }
+    let mut z_r = Lanes::splat(0.0);
+    let mut z_i = Lanes::splat(0.0);
+    let mut iterations = Simd::<u32, LANES>::splat(0);
+    let mut periodic = Mask::splat(false);
+    let (mut reference_r, mut reference_i) = (z_r, z_i);
+    let mut check_interval = PERIODICITY_CHECK_INTERVAL;
+    let mut steps_since_reference = 0;
+    for _ in 0..desc.max_iterations {
+        if !active.any() {
+            break;
+        }
+        // Same operations as Complex multiplication so the orbits match the scalar path exactly
+        let next_r = z_r*z_r - z_i*z_i + c_r;
+        let next_i = z_r*z_i + z_i*z_r + c_i;
+        z_r = active.select(next_r, z_r);
+        z_i = active.select(next_i, z_i);
+        iterations += active.cast::<i32>().select(Simd::splat(1), Simd::splat(0));
+        active &= (z_r*z_r + z_i*z_i).simd_le(bailout_squared);
+
+        let repeated = active & z_r.simd_eq(reference_r) & z_i.simd_eq(reference_i);
+        periodic |= repeated;
+        active &= !repeated;
+        steps_since_reference += 1;
+        if steps_since_reference == check_interval {
+            reference_r = z_r;
+            reference_i = z_i;
+            steps_since_reference = 0;
+            check_interval *= 2;
+        }
+    }
+
+    let (z_r, z_i, iterations, periodic) = (z_r.to_array(), z_i.to_array(), iterations.to_array(), periodic.to_array());
+    for lane in 0..positions.len() {
+        let z = Complex {r: z_r[lane], i: z_i[lane]};
+        let escaped = z.length_squared() > desc.bailout*desc.bailout;
+        let total = if inside[lane] || periodic[lane] || !escaped { desc.max_iterations } else { iterations[lane] };
+        orbits.push(Orbit {iterations: total, computed: iterations[lane], escaped, z, dz: Complex {r: 0.0, i: 0.0}, trap_distance: Real::INFINITY, interior_distance: None});
+    }
+}
+
+// Same as thread_worker, the samples of a row are placed first in the same order
+// so the rng sequence and a seeded image don't change, then iterated LANES at a time
+pub(crate) fn thread_worker<R: Rng>(rows: &mut [&mut [Color]], desc: &ThreadDescryptor, palette: &Palette, cdf: &[Real], pixels_done: &AtomicUsize, rng: &mut R) -> Work {
+    let mut work = Work::default();
+    let mut positions = Vec::new();
+    let mut orbits = Vec::new();
+    for (y, row) in rows.iter_mut().enumerate() {
+        positions.clear();
+        orbits.clear();
+        for x in 0..row.len() {
+            for sample in 0..desc.sample_count {
+                let jitter = sample_offset(desc, sample, rng);
+                positions.push(pixel_position(desc, x+desc.offset.x, y+desc.offset.y, jitter));
+            }
+        }
+        for chunk in positions.chunks(LANES) {
+            iterate_lanes(desc, chunk, &mut orbits);
+        }
+
+        for (pixel, samples) in row.iter_mut().zip(orbits.chunks(desc.sample_count)) {
+            let mut pixel_color = Color::new();
+            for orbit in samples {
+                work.samples += 1;
+                work.iterations += orbit.computed as u64;
+                pixel_color = pixel_color + sample_color(desc, palette, cdf, orbit);
+            }
+            *pixel = pixel_color / desc.sample_count as Real;
+        }
+        work.pixels += row.len() as u64;
+        pixels_done.fetch_add(row.len(), Ordering::Relaxed);
+    }
+    work
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{RenderConfig, iterate};
+
+    #[test]
+    fn lanes_match_the_scalar_iteration() {
+        let mut config = RenderConfig::new();
+        config.max_iterations = 500;
+        let desc = ThreadDescryptor::from_config(&config);
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let positions: Vec<Complex> = (0..LANES - 1).map(|_| Complex {r: rng.gen_range(-2.0, 1.0), i: rng.gen_range(-1.5, 1.5)}).collect();
+            let mut orbits = Vec::new();
+            iterate_lanes(&desc, &positions, &mut orbits);
+            for (pos, orbit) in positions.iter().zip(&orbits) {
+                let expected = iterate(&desc, pos);
+                assert_eq!(orbit.iterations, expected.iterations);
+                assert_eq!(orbit.computed, expected.computed);
+                assert_eq!(orbit.escaped, expected.escaped);
+                if expected.escaped {
+                    assert_eq!(orbit.z, expected.z);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// C ABI for WebAssembly hosts, JavaScript calls these through the exports of the instance and
+// reads the pixels straight out of its memory into an ImageData for a canvas
+use super::{RenderConfig, Real, Vec2, render_to_rgba};
+
+// Renders width x height pixels around center_x, center_y with a view zoom high and returns a
+// pointer to the width*height*4 RGBA bytes, to be released with mandelbrot_free. Returns null
+// if the view is invalid
+#[no_mangle]
+pub extern "C" fn mandelbrot_render(width: u32, height: u32, center_x: f64, center_y: f64, zoom: f64, samples: u32, max_iterations: u32) -> *mut u8 {
+    let mut config = RenderConfig::new();
+    config.width = width as usize;
+    config.height = height as usize;
+    config.center = Vec2::<Real>{x: center_x as Real, y: center_y as Real};
+    config.set_zoom(zoom as Real);
+    config.sample_count = samples as usize;
+    config.max_iterations = max_iterations;
+    config.thread_count = 1;
+    config.progress = false;
+    if config.validate().is_err() {
+        return std::ptr::null_mut();
+    }
+    Box::into_raw(render_to_rgba(&config).into_boxed_slice()) as *mut u8
+}
+
+// Releases the len bytes at pixels returned by mandelbrot_render
+//
+// # Safety
+// pixels must come from mandelbrot_render with the same width*height*4 as len, and is freed once
+#[no_mangle]
+pub unsafe extern "C" fn mandelbrot_free(pixels: *mut u8, len: usize) {
+    if !pixels.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(pixels, len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_render_returns_opaque_rgba() {
+        let pixels = mandelbrot_render(16, 12, -0.5, 0.0, 2.5, 4, 64);
+        let len = 16 * 12 * 4;
+        unsafe {
+            let rgba = std::slice::from_raw_parts(pixels, len);
+            assert!(rgba.chunks(4).all(|pixel| pixel[3] == 255));
+            // The center of the view is inside the set
+            assert_eq!(&rgba[(6 * 16 + 8) * 4..(6 * 16 + 8) * 4 + 3], &[0, 0, 0]);
+            mandelbrot_free(pixels, len);
+        }
+        assert!(mandelbrot_render(0, 12, -0.5, 0.0, 2.5, 1, 64).is_null());
+    }
+}
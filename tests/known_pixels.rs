@@ -0,0 +1,46 @@
+// Renders of the whole set through the public API, checked at pixels whose fate is known
+use mandelbrot_set::{Color, RenderConfig, Real, Vec2, render_iterations, render_mandelbrot};
+
+const SIZE: usize = 64;
+
+// The square [-2.25:2.25] x [-2.25:2.25] of the complex plane at one sample per pixel
+fn whole_set() -> RenderConfig {
+    let mut config = RenderConfig::new();
+    config.width = SIZE;
+    config.height = SIZE;
+    config.center = Vec2::<Real>{x: 0.0, y: 0.0};
+    config.set_zoom(4.5);
+    config.sample_count = 1;
+    config.seed = Some(91);
+    config.progress = false;
+    config
+}
+
+// Index of the pixel containing the point re + im*i of the whole_set view
+fn pixel_of(re: Real, im: Real) -> usize {
+    let x = ((re + 2.25) / 4.5 * SIZE as Real) as usize;
+    let y = ((2.25 - im) / 4.5 * SIZE as Real) as usize;
+    y * SIZE + x
+}
+
+#[test]
+fn origin_is_inside_the_set() {
+    let config = whole_set();
+    let iterations = render_iterations(&config);
+    assert_eq!(iterations[pixel_of(0.0, 0.0)], -1.0);
+    let colors = render_mandelbrot(&config);
+    assert_eq!(colors[pixel_of(0.0, 0.0)], Color::rgb(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn distant_points_escape_at_once() {
+    let config = whole_set();
+    let iterations = render_iterations(&config);
+    let colors = render_mandelbrot(&config);
+    // |c| > 2 escapes on the first iteration, the corners of the view are further out still
+    for (re, im) in [(2.0, 2.0), (-2.0, 2.0), (2.0, -2.0), (-2.2, -2.2)] {
+        let pixel = pixel_of(re, im);
+        assert!((0.0..2.0).contains(&iterations[pixel]), "{} iterations at {},{}", iterations[pixel], re, im);
+        assert_ne!(colors[pixel], Color::rgb(0.0, 0.0, 0.0), "{},{} is black", re, im);
+    }
+}
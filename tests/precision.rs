@@ -0,0 +1,66 @@
+// Renders of a shallow view checked against the escape time iterated in f64, whichever Real the
+// renderer was built with: f32 is enough at this zoom and f64 has to map the pixels the same way
+use mandelbrot_set::{RenderConfig, Real, Vec2, render_iterations};
+
+const WIDTH: usize = 96;
+const HEIGHT: usize = 64;
+const MAX_ITERATIONS: u32 = 250;
+
+// Part of seahorse valley about 0.3 high, far from the zooms of about 1e-5 where f32 breaks up
+fn shallow_view() -> RenderConfig {
+    let mut config = RenderConfig::new();
+    config.width = WIDTH;
+    config.height = HEIGHT;
+    config.center = Vec2::<Real>{x: -0.75, y: 0.125};
+    config.set_zoom(0.3);
+    config.max_iterations = MAX_ITERATIONS;
+    config.progress = false;
+    config
+}
+
+// Continuous iteration count at the top left corner of pixel x, y like render_iterations
+// gives it, -1 inside the set, with the bailout 2 of the default config
+#[allow(clippy::unnecessary_cast)]
+fn reference_iterations(config: &RenderConfig, x: usize, y: usize) -> f64 {
+    let re = config.center.x as f64 + ((x as f64) / (WIDTH as f64) * 2.0 - 1.0) * config.view_size.x as f64 / 2.0;
+    let im = config.center.y as f64 - ((y as f64) / (HEIGHT as f64) * 2.0 - 1.0) * config.view_size.y as f64 / 2.0;
+    let (mut zr, mut zi) = (0.0f64, 0.0f64);
+    for iteration in 1..=MAX_ITERATIONS {
+        let next = zr*zr - zi*zi + re;
+        zi = 2.0*zr*zi + im;
+        zr = next;
+        let length_squared = zr*zr + zi*zi;
+        if length_squared > 4.0 {
+            return iteration as f64 + 1.0 - (length_squared.sqrt().ln().ln() / 2.0f64.ln());
+        }
+    }
+    -1.0
+}
+
+#[test]
+fn shallow_views_match_the_f64_iteration() {
+    let config = shallow_view();
+    let iterations = render_iterations(&config);
+    let mut inside_mismatches = 0;
+    let mut count_mismatches = 0;
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let expected = reference_iterations(&config, x, y);
+            let rendered = iterations[y*WIDTH + x] as f64;
+            if (expected < 0.0) != (rendered < 0.0) {
+                inside_mismatches += 1;
+            }
+            else if (expected - rendered).abs() > 0.01 {
+                count_mismatches += 1;
+            }
+        }
+    }
+    // In f32 rounding parts the orbits of a few points close to the boundary, f64 follows the reference
+    let pixels = WIDTH * HEIGHT;
+    let (inside_limit, count_limit) = match cfg!(feature = "f64") {
+        true => (0, 0),
+        false => (pixels / 200, pixels / 50),
+    };
+    assert!(inside_mismatches <= inside_limit, "{} of {} pixels inside in only one", inside_mismatches, pixels);
+    assert!(count_mismatches <= count_limit, "{} of {} pixels escape at other counts", count_mismatches, pixels);
+}